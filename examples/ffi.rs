@@ -0,0 +1,55 @@
+//! Reading a channel from C through a raw reader handle
+//! ```shell
+//! $ cargo run --example ffi
+//! ```
+//! The C side only sees the handle and the exported functions:
+//! ```c
+//! void read_i32(const void *handle, int32_t *out);
+//! void close_i32(const void *handle);
+//!
+//! int32_t latest;
+//! read_i32(handle, &latest);
+//! ```
+
+#![cfg_attr(loom, allow(unused))]
+
+use std::ffi::c_void;
+
+#[cfg(not(loom))]
+use cupchan::{cupchan, cupchan_read, CupchanReader};
+
+/// # Safety
+/// See [`cupchan_read`]
+#[cfg(not(loom))]
+#[no_mangle]
+pub unsafe extern "C" fn read_i32(handle: *const c_void, out: *mut i32) {
+	cupchan_read::<i32>(handle, out)
+}
+
+/// # Safety
+/// `handle` must come from a `CupchanReader<i32>` and can't be used afterwards
+#[cfg(not(loom))]
+#[no_mangle]
+pub unsafe extern "C" fn close_i32(handle: *const c_void) {
+	drop(CupchanReader::<i32>::from_raw(handle));
+}
+
+// Loom builds don't have cupchan_read
+#[cfg(loom)]
+fn main() {}
+
+#[cfg(not(loom))]
+fn main() {
+	let (mut writer, reader) = cupchan(0i32);
+	let handle = reader.into_raw();
+
+	// What the C consumer would do
+	writer.write_and_flush(42);
+	let mut latest = 0;
+	unsafe { read_i32(handle, &mut latest) };
+	println!("read {latest}");
+	assert_eq!(latest, 42);
+
+	unsafe { close_i32(handle) };
+	assert!(!writer.is_reader_connected());
+}
@@ -0,0 +1,22 @@
+//! Checks that the channel can be used from a `#![no_std]` crate
+//! ```shell
+//! $ cargo build --example no_std --no-default-features
+//! ```
+#![no_std]
+// Reads and writes go through Deref, which loom builds don't have
+#![cfg(not(loom))]
+
+use cupchan::{cupchan, CupchanReader, CupchanWriter};
+
+pub fn channel() -> (CupchanWriter<u32>, CupchanReader<u32>) {
+	cupchan(0)
+}
+
+pub fn send(writer: &mut CupchanWriter<u32>, value: u32) {
+	**writer = value;
+	writer.flush();
+}
+
+pub fn latest(reader: &CupchanReader<u32>) -> Option<u32> {
+	reader.changed().then(|| **reader)
+}
@@ -0,0 +1,128 @@
+//! Cup channel for values that fit in a single atomic, which don't need cups to be swapped at all
+
+use core::fmt;
+
+use crate::Arc;
+#[cfg(not(loom))]
+use crate::{Deref, DerefMut};
+
+mod sealed {
+	/// Keeps [`AtomicCopy`](super::AtomicCopy) from being implemented outside this crate
+	pub trait Sealed {}
+}
+
+/// Values that can be published with a single atomic store, see [`cupchan_copy`].
+/// Implemented for `bool` and the integer types, and sealed so no other types can implement it.
+pub trait AtomicCopy: Copy + sealed::Sealed {
+	/// Atomic holding the value
+	#[doc(hidden)]
+	type Atomic: fmt::Debug + Send + Sync;
+	#[doc(hidden)]
+	fn new_atomic(self) -> Self::Atomic;
+	#[doc(hidden)]
+	fn load(atomic: &Self::Atomic) -> Self;
+	#[doc(hidden)]
+	fn store(self, atomic: &Self::Atomic);
+}
+
+macro_rules! atomic_copy {
+	($($(#[$attr:meta])* $ty:ty => $atomic:ident),* $(,)?) => {$(
+		$(#[$attr])*
+		impl sealed::Sealed for $ty {}
+		$(#[$attr])*
+		impl AtomicCopy for $ty {
+			#[cfg(not(loom))]
+			type Atomic = core::sync::atomic::$atomic;
+			#[cfg(loom)]
+			type Atomic = loom::sync::atomic::$atomic;
+			#[inline]
+			fn new_atomic(self) -> Self::Atomic {
+				Self::Atomic::new(self)
+			}
+			#[inline]
+			fn load(atomic: &Self::Atomic) -> Self {
+				atomic.load(crate::Ordering::Acquire)
+			}
+			#[inline]
+			fn store(self, atomic: &Self::Atomic) {
+				atomic.store(self, crate::Ordering::Release)
+			}
+		}
+	)*};
+}
+atomic_copy!(
+	bool => AtomicBool,
+	u8 => AtomicU8,
+	u16 => AtomicU16,
+	u32 => AtomicU32,
+	#[cfg(target_has_atomic = "64")]
+	u64 => AtomicU64,
+	usize => AtomicUsize,
+	i8 => AtomicI8,
+	i16 => AtomicI16,
+	i32 => AtomicI32,
+	#[cfg(target_has_atomic = "64")]
+	i64 => AtomicI64,
+	isize => AtomicIsize,
+);
+
+/// Create a new Cup Channel for a value that fits in an atomic.
+/// Flushing is a single atomic store and reading a single atomic load, instead of swapping cups around.
+/// ```rust
+/// use cupchan::cupchan_copy;
+///
+/// let (mut writer, reader) = cupchan_copy(0u64);
+/// *writer = 1;
+/// writer.flush();
+/// assert_eq!(reader.read(), 1);
+/// ```
+pub fn cupchan_copy<T: AtomicCopy>(initial: T) -> (CupchanCopyWriter<T>, CupchanCopyReader<T>) {
+	let chan = Arc::new(initial.new_atomic());
+	(
+		CupchanCopyWriter {
+			chan: chan.clone(),
+			value: initial,
+		},
+		CupchanCopyReader { chan },
+	)
+}
+
+/// Write to a [`cupchan_copy`] channel through its deref, make sure to call flush() afterwards.
+#[derive(Debug)]
+pub struct CupchanCopyWriter<T: AtomicCopy> {
+	chan: Arc<T::Atomic>,
+	/// Value that will be published by the next flush
+	value: T,
+}
+impl<T: AtomicCopy> CupchanCopyWriter<T> {
+	/// Publish the written value to the reader
+	pub fn flush(&mut self) {
+		self.value.store(&self.chan);
+	}
+}
+#[cfg(not(loom))]
+impl<T: AtomicCopy> Deref for CupchanCopyWriter<T> {
+	type Target = T;
+	fn deref(&self) -> &Self::Target {
+		&self.value
+	}
+}
+#[cfg(not(loom))]
+impl<T: AtomicCopy> DerefMut for CupchanCopyWriter<T> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.value
+	}
+}
+
+/// Read from a [`cupchan_copy`] channel, the value is copied out so the reader can also be cloned.
+/// There is no cup to borrow the value from, so unlike [`CupchanReader`](crate::CupchanReader) this doesn't implement `Deref`.
+#[derive(Debug, Clone)]
+pub struct CupchanCopyReader<T: AtomicCopy> {
+	chan: Arc<T::Atomic>,
+}
+impl<T: AtomicCopy> CupchanCopyReader<T> {
+	/// Copy out the latest flushed value
+	pub fn read(&self) -> T {
+		T::load(&self.chan)
+	}
+}
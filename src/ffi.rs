@@ -0,0 +1,32 @@
+//! Raw reader handles for passing a channel across an FFI boundary, see `examples/ffi.rs`
+
+use core::{ffi::c_void, mem::ManuallyDrop, ptr};
+
+use crate::{Arc, CupchanReader};
+
+impl<T> CupchanReader<T> {
+	/// Turn this reader into an opaque handle, the channel stays connected until the handle is passed to [`from_raw`](Self::from_raw) and dropped.
+	pub fn into_raw(self) -> *const c_void {
+		// Skip Drop, the reader lives on in the handle
+		let this = ManuallyDrop::new(self);
+		Arc::into_raw(unsafe { ptr::read(&this.chan) }) as *const c_void
+	}
+	/// Get the reader back from a handle created by [`into_raw`](Self::into_raw).
+	///
+	/// # Safety
+	/// `handle` must come from `CupchanReader::<T>::into_raw` with the same `T`, and must not be used again afterwards.
+	pub unsafe fn from_raw(handle: *const c_void) -> Self {
+		Self::new(Arc::from_raw(handle as *const _))
+	}
+}
+
+/// Copy the latest value of the channel behind `handle` into `out`, swapping it in like a deref of the reader would.
+/// Meant to be exported with a concrete `T` for C code to call, the handle stays owned by the caller.
+///
+/// # Safety
+/// `handle` must be a live handle from `CupchanReader::<T>::into_raw`, only used by one thread at a time, and `out` must be valid for writes.
+#[cfg(not(loom))]
+pub unsafe extern "C" fn cupchan_read<T: Copy>(handle: *const c_void, out: *mut T) {
+	let reader = ManuallyDrop::new(CupchanReader::<T>::from_raw(handle));
+	out.write(**reader);
+}
@@ -23,23 +23,83 @@
 //! writer.flush();
 //! assert_eq!(*reader, 3);
 //! ```
-#![feature(test)]
+#![cfg_attr(all(test, feature = "nightly"), feature(test))]
+#![cfg_attr(all(feature = "nightly", not(loom)), feature(allocator_api))]
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 
-use std::{fmt, ptr};
+extern crate alloc;
+
+mod copy;
+mod ffi;
+mod map;
+mod ring;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod spmc;
+#[cfg(feature = "async")]
+mod stream;
+#[cfg(feature = "async")]
+mod waker;
+pub use copy::{cupchan_copy, AtomicCopy, CupchanCopyReader, CupchanCopyWriter};
+#[cfg(not(loom))]
+pub use ffi::cupchan_read;
+pub use map::{MappedReader, MappedRefReader};
+pub use ring::{cupchan_n, CupchanNReader, CupchanNWriter};
+pub use spmc::{
+	cupchan_broadcast, cupchan_spmc, BroadcastReader, CupchanSharedReader, ReaderFactory,
+};
+#[cfg(feature = "async")]
+pub use stream::{Changed, CupchanStream, FlushFuture};
+
+use core::{
+	error::Error,
+	fmt, mem,
+	ops::Deref,
+	ptr::{self, NonNull},
+};
 
 #[cfg(loom)]
 pub(crate) use loom::{
 	cell::{ConstPtr, MutPtr, UnsafeCell},
-	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	sync::{
+		atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+		Arc,
+	},
+	thread::Thread,
 };
 
 #[cfg(not(loom))]
-pub(crate) use std::{
+pub(crate) use alloc::sync::Arc;
+#[cfg(not(loom))]
+pub(crate) use core::{
 	cell::UnsafeCell,
-	ops::{Deref, DerefMut},
-	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	marker::PhantomData,
+	ops::DerefMut,
+	sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+};
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::{
+	thread::{self, Thread},
+	time::{Duration, Instant},
 };
 
+/// Stand-in for a thread handle, threads can't be parked without std
+#[cfg(all(not(loom), not(feature = "std")))]
+enum Thread {}
+
+/// Stored in `Cupchan::waiter` once the writer is dropped or disconnected so a reader won't park waiting for it
+const CLOSED_WAITER: *mut Thread = NonNull::dangling().as_ptr();
+
+/// States of `Cupchan::reader_state` and `Cupchan::writer_state`: the endpoint is in use
+const ALIVE: u8 = 0;
+/// The endpoint called disconnect() but is still alive, so it can't be replaced yet
+const DISCONNECTED: u8 = 1;
+/// The endpoint was dropped, the other one may create a new one
+const DROPPED: u8 = 2;
+
+/// Set in `state` when the storage cup holds a flushed value the reader has not yet swapped to
+const UPDATE_FLAG: usize = 0b1000;
+
 const OBJECT_PERMUTATIONS: &[usize; 6] = &[
 	0b000, // <W><S><R>
 	0b001, // <W><R><S>
@@ -107,32 +167,233 @@ const READER_STATE_MAP: &[usize; 16] = &[
 ];
 const READER_CUP_MAP: &[usize; 16] = &[2, 1, 1, 2, 0, 0, 3, 3, 2, 1, 1, 2, 0, 0, 3, 3];
 
+/// Aligns its contents to a cache line so that neighbouring fields written by different threads don't share one.
+/// Building with `--cfg cupchan_unpadded` turns this into a plain wrapper, to benchmark the channel without padding.
+#[derive(Debug)]
+#[cfg_attr(not(cupchan_unpadded), repr(align(64)))]
+struct CachePadded<T>(T);
+impl<T> Deref for CachePadded<T> {
+	type Target = T;
+	#[inline]
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
 /// A simple async channel used to quickly update data between threads
 /// Useful in a situation where you need to model some read-only state on a receiving thread that can be periodically, but quickly, updated from a writer thread.
 struct Cupchan<T> {
 	// One of these cups is reading, one writing, one for intermediate storage, which one is which depends on the permutation state
-	cups: [UnsafeCell<T>; 3], // Use boxes to avoid False Sharing between cpu cache lines https://en.wikipedia.org/wiki/False_sharing
+	cups: [CachePadded<UnsafeCell<T>>; 3], // Pad to avoid False Sharing between cpu cache lines https://en.wikipedia.org/wiki/False_sharing
 	/// Represents the permutation of cups i.e. which one is the reader, writer, and storage as well as whether or not storage is ready to be read from.
-	state: AtomicUsize,
-	/// True if reader or writer is dropped
-	unconnected: AtomicBool,
+	state: CachePadded<AtomicUsize>,
+	/// Number of flushes, only modified by the writer
+	generation: CachePadded<AtomicUsize>,
+	/// Generation at which each cup was last flushed
+	cup_generations: CachePadded<[AtomicUsize; 3]>,
+	/// Number of flushes that overwrote a value the reader never read, only modified by the writer
+	dropped: CachePadded<AtomicUsize>,
+	/// ALIVE, DISCONNECTED or DROPPED. Only used to allow reconnecting, freeing the channel is left to the `Arc`
+	reader_state: AtomicU8,
+	/// Same as `reader_state` for the writer
+	writer_state: AtomicU8,
+	/// Boxed handle of the reader thread parked in recv(), null if no reader is waiting, CLOSED_WAITER if the writer was dropped
+	waiter: AtomicPtr<Thread>,
+	/// Set once the reader calls wait(), after which every flush() checks for a parked reader
+	#[cfg(feature = "std")]
+	wait_used: AtomicBool,
+	/// Waker of a pending CupchanStream or Changed future, woken on flush
+	#[cfg(feature = "async")]
+	stream_waker: waker::AtomicWaker,
+	/// Waker of a pending FlushFuture, woken when the reader consumes a flush
+	#[cfg(feature = "async")]
+	writer_waker: waker::AtomicWaker,
+}
+impl<T> Cupchan<T> {
+	fn new([a, b, c]: [T; 3]) -> Self {
+		Cupchan {
+			cups: [a, b, c].map(|cup| CachePadded(UnsafeCell::new(cup))),
+			state: CachePadded(AtomicUsize::new(OBJECT_PERMUTATIONS[0])), // Initial state: <W><S><R> permutation with UPDATE_FLAG unset
+			generation: CachePadded(AtomicUsize::new(0)),
+			cup_generations: CachePadded([
+				AtomicUsize::new(0),
+				AtomicUsize::new(0),
+				AtomicUsize::new(0),
+			]),
+			dropped: CachePadded(AtomicUsize::new(0)),
+			reader_state: AtomicU8::new(ALIVE),
+			writer_state: AtomicU8::new(ALIVE),
+			waiter: AtomicPtr::new(ptr::null_mut()),
+			#[cfg(feature = "std")]
+			wait_used: AtomicBool::new(false),
+			#[cfg(feature = "async")]
+			stream_waker: waker::AtomicWaker::new(),
+			#[cfg(feature = "async")]
+			writer_waker: waker::AtomicWaker::new(),
+		}
+	}
+	/// Current permutation state, decoded
+	fn raw_state(&self) -> DebugState {
+		let state = self.state.load(Ordering::SeqCst);
+		let (writer, reader) = (WRITER_CUP_MAP[state], READER_CUP_MAP[state]);
+		DebugState {
+			writer,
+			reader,
+			storage: 3 - writer - reader,
+			updated: state & UPDATE_FLAG != 0,
+		}
+	}
+	/// Index of the cup holding the most recently flushed value
+	#[cfg(not(loom))]
+	fn published_cup(&self) -> usize {
+		let state = self.state.load(Ordering::Acquire);
+		if state & UPDATE_FLAG != 0 {
+			// Storage is the cup that's neither writing nor reading
+			3 - WRITER_CUP_MAP[state] - READER_CUP_MAP[state]
+		} else {
+			READER_CUP_MAP[state]
+		}
+	}
+	/// True once the writer has been dropped or disconnected (and no new writer created)
+	#[cfg(any(feature = "async", all(not(loom), feature = "std")))]
+	#[inline]
+	fn writer_dropped(&self) -> bool {
+		self.waiter.load(Ordering::Acquire) == CLOSED_WAITER
+	}
+	/// Take the place of the endpoint whose state is `endpoint`, returns false unless it was dropped.
+	/// A disconnected endpoint is still alive and may be borrowing its cup, so it can't be replaced yet.
+	fn reconnect(endpoint: &AtomicU8) -> bool {
+		// Acquire the dropped endpoint's last accesses to its cup
+		endpoint
+			.compare_exchange(DROPPED, ALIVE, Ordering::SeqCst, Ordering::SeqCst)
+			.is_ok()
+	}
+	/// Unpark the reader if it is waiting in recv(), only does an atomic load if it isn't
+	#[cfg(feature = "std")]
+	#[inline]
+	fn wake_reader(&self) {
+		// Only the writer stores CLOSED_WAITER, after disconnect() it stays there until the writer is dropped
+		let waiter = self.waiter.load(Ordering::Acquire);
+		if !waiter.is_null() && waiter != CLOSED_WAITER {
+			let waiter = self.waiter.swap(ptr::null_mut(), Ordering::AcqRel);
+			if !waiter.is_null() {
+				unsafe { Box::from_raw(waiter) }.unpark();
+			}
+		}
+	}
 }
 /// Create a new Cup Channel
 pub fn cupchan<T: Clone>(initial: T) -> (CupchanWriter<T>, CupchanReader<T>) {
-	let chan = Cupchan {
-		cups: [
-			UnsafeCell::new(initial.clone()),
-			UnsafeCell::new(initial.clone()),
-			UnsafeCell::new(initial),
-		],
-		state: AtomicUsize::new(OBJECT_PERMUTATIONS[0]), // Initial state: <W><S><R> permutation with UPDATE_FLAG unset
-		unconnected: AtomicBool::new(false),
+	// Nothing is allocated until all cups exist, if a clone panics the cups built so far are dropped with the array
+	endpoints(Arc::new(Cupchan::new([
+		initial.clone(),
+		initial.clone(),
+		initial,
+	])))
+}
+/// Create a new Cup Channel without requiring `T: Clone`, `init` is called once for each of the three cups.
+/// Also useful to preallocate every cup, as each one gets its own independent buffer:
+/// ```rust
+/// # use cupchan::cupchan_with;
+/// let (mut writer, reader) = cupchan_with(|| Vec::<u8>::with_capacity(1024));
+/// writer.extend_from_slice(b"no reallocation");
+/// writer.flush();
+/// assert_eq!(reader.capacity(), 1024);
+/// ```
+pub fn cupchan_with<T, F: FnMut() -> T>(mut init: F) -> (CupchanWriter<T>, CupchanReader<T>) {
+	endpoints(Arc::new(Cupchan::new([init(), init(), init()])))
+}
+/// Create a new Cup Channel with every cup set to `T::default()`
+/// ```rust
+/// use cupchan::cupchan_default;
+///
+/// let (mut writer, reader) = cupchan_default::<i32>();
+/// assert_eq!(*reader, 0);
+///
+/// *writer = 1;
+/// writer.flush();
+/// assert_eq!(*reader, 1);
+///
+/// drop(reader);
+///
+/// let reader = writer.new_reader().unwrap(); // Create a new reader
+///
+/// *writer = 2;
+/// writer.flush();
+/// assert_eq!(*reader, 2);
+/// ```
+pub fn cupchan_default<T: Default>() -> (CupchanWriter<T>, CupchanReader<T>) {
+	cupchan_with(T::default)
+}
+/// Builder for channels with non-default options, [`cupchan`] is the shortcut for the defaults.
+/// ```rust
+/// # use cupchan::CupchanBuilder;
+/// let (mut writer, reader) = CupchanBuilder::new(0).build();
+/// writer.write_and_flush(1);
+/// assert_eq!(*reader, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CupchanBuilder<T> {
+	initial: T,
+	#[cfg(feature = "std")]
+	notify_on_flush: bool,
+}
+impl<T: Clone> CupchanBuilder<T> {
+	/// Start building a channel with every cup set to `initial`
+	pub fn new(initial: T) -> Self {
+		Self {
+			initial,
+			#[cfg(feature = "std")]
+			notify_on_flush: false,
+		}
+	}
+	/// Make every [`flush`](CupchanWriter::flush) wake up a reader blocked in [`recv`](CupchanReader::recv), like [`wait`](CupchanReader::wait) does once called.
+	/// ```rust
+	/// # use cupchan::CupchanBuilder;
+	/// # use std::thread;
+	/// let (mut writer, mut reader) = CupchanBuilder::new(0).notify_on_flush(true).build();
+	/// let join = thread::spawn(move || *reader.recv().unwrap());
+	/// writer.write_and_flush(1);
+	/// assert_eq!(join.join().unwrap(), 1);
+	/// ```
+	#[cfg(feature = "std")]
+	pub fn notify_on_flush(mut self, notify: bool) -> Self {
+		self.notify_on_flush = notify;
+		self
+	}
+	/// Create the channel
+	pub fn build(self) -> (CupchanWriter<T>, CupchanReader<T>) {
+		let endpoints = cupchan(self.initial);
+		#[cfg(feature = "std")]
+		endpoints
+			.0
+			.chan
+			.wait_used
+			.store(self.notify_on_flush, Ordering::Relaxed);
+		endpoints
+	}
+}
+/// Create a new Cup Channel, returning `initial` instead of aborting if the channel can't be allocated.
+/// Only the channel allocation is fallible, cloning `initial` into the cups may still abort.
+/// Requires the `nightly` feature for the allocator api.
+#[cfg(all(feature = "nightly", not(loom)))]
+pub fn try_cupchan<T: Clone>(initial: T) -> Result<(CupchanWriter<T>, CupchanReader<T>), T> {
+	let Ok(mut chan) = Arc::try_new_uninit() else {
+		return Err(initial);
 	};
-	let chan = Box::leak(Box::new(chan)); // Use special dropping logic based on self.unconnected
+	Arc::get_mut(&mut chan).unwrap().write(Cupchan::new([
+		initial.clone(),
+		initial.clone(),
+		initial,
+	]));
+	Ok(endpoints(unsafe { chan.assume_init() }))
+}
+fn endpoints<T>(chan: Arc<Cupchan<T>>) -> (CupchanWriter<T>, CupchanReader<T>) {
+	// Channel is freed once both ends are dropped
 	(
 		CupchanWriter {
-			chan,
-			current_cup: &chan.cups[0],
+			chan: chan.clone(),
+			current_cup: 0,
 		},
 		CupchanReader { chan },
 	)
@@ -142,28 +403,48 @@ impl<T: fmt::Debug> fmt::Debug for Cupchan<T> {
 		f.debug_struct("Cupchan")
 			.field("cups", &self.cups)
 			.field("state", &self.state.load(Ordering::SeqCst))
-			.field("unconnected", &self.unconnected.load(Ordering::SeqCst))
+			.field("reader_state", &self.reader_state.load(Ordering::SeqCst))
+			.field("writer_state", &self.writer_state.load(Ordering::SeqCst))
 			.finish()
 	}
 }
 
 /// Write to the Cup Channel, make sure to call flush() afterwards.
-#[derive(Debug)]
-pub struct CupchanWriter<T: 'static> {
-	chan: &'static Cupchan<T>,
-	current_cup: &'static UnsafeCell<T>,
+///
+/// The writing cup is borrowed through [`DerefMut`], which has to end before [`flush`](Self::flush) as both take `&mut self`.
+/// A reference to the cup can't be carried over into the flush that hands it to the reader:
+/// ```rust,compile_fail
+/// # use cupchan::cupchan;
+/// let (mut writer, _reader) = cupchan(0);
+/// let cup = &mut *writer;
+/// writer.flush();
+/// *cup = 1;
+/// ```
+///
+/// Dropping the writer without ever flushing leaves the reader with the initial value.
+/// The reader can tell this happened from a [`generation`](CupchanReader::generation) of 0 once the writer is disconnected.
+#[must_use = "the writer must be retained to publish updates; dropping it disconnects the reader"]
+pub struct CupchanWriter<T> {
+	chan: Arc<Cupchan<T>>,
+	/// Index of the cup currently being written to
+	current_cup: usize,
 }
 impl<T> CupchanWriter<T> {
-	fn new(chan: &'static Cupchan<T>) -> Self {
-		let cup_index = WRITER_CUP_MAP[chan.state.load(Ordering::Acquire)];
-		Self {
-			chan,
-			current_cup: &chan.cups[cup_index],
-		}
+	fn new(chan: Arc<Cupchan<T>>) -> Self {
+		let current_cup = WRITER_CUP_MAP[chan.state.load(Ordering::Acquire)];
+		chan.waiter.store(ptr::null_mut(), Ordering::Release); // Clear CLOSED_WAITER left by the previous writer
+		Self { chan, current_cup }
 	}
-	pub fn flush(&mut self) {
+	/// Publish the writing cup to the reader.
+	/// Returns true if the reader had consumed the previously flushed value, false if that value was overwritten without being read.
+	pub fn flush(&mut self) -> bool {
 		// Needs exclusive reference
+		// Tag the cup with its generation, published to the reader by the state update below
+		let generation = self.chan.generation.load(Ordering::Relaxed) + 1;
+		self.chan.cup_generations[self.current_cup].store(generation, Ordering::Relaxed);
+		self.chan.generation.store(generation, Ordering::Release);
 		// Update storage flag & swap cups
+		// Release publishes the writing cup to the reader, Acquire pairs with the reader's Release so it is done with the cup taken back
 		let res = self
 			.chan
 			.state
@@ -171,66 +452,425 @@ impl<T> CupchanWriter<T> {
 				Some(state ^ WRITER_STATE_MAP[state])
 			})
 			.unwrap();
-		self.current_cup = &self.chan.cups[WRITER_CUP_MAP[res ^ WRITER_STATE_MAP[res]]];
+		self.current_cup = WRITER_CUP_MAP[res ^ WRITER_STATE_MAP[res]];
+		if res & UPDATE_FLAG != 0 {
+			let dropped = self.chan.dropped.load(Ordering::Relaxed) + 1;
+			self.chan.dropped.store(dropped, Ordering::Relaxed);
+		}
+		#[cfg(feature = "std")]
+		if self.chan.wait_used.load(Ordering::Relaxed) {
+			self.chan.wake_reader();
+		}
+		#[cfg(feature = "async")]
+		self.chan.wake_stream();
+		res & UPDATE_FLAG == 0
+	}
+	/// Flush only if the reader has read the previous flush, so no value is ever overwritten unread.
+	/// Otherwise returns [`FlushBlocked`] without touching the channel, the writing cup keeps its value to try again later.
+	pub fn try_flush(&mut self) -> Result<(), FlushBlocked> {
+		// Only the writer sets the update flag, so once it's seen clear the flush below can't overwrite anything
+		if self.chan.state.load(Ordering::Acquire) & UPDATE_FLAG != 0 {
+			return Err(FlushBlocked);
+		}
+		self.flush();
+		Ok(())
+	}
+	/// Flush and wake up the reader if it is blocked in [`CupchanReader::recv`].
+	/// Only costs an extra atomic load over [`flush`](Self::flush) if no reader is waiting.
+	#[cfg(feature = "std")]
+	pub fn flush_notify(&mut self) -> bool {
+		let consumed = self.flush();
+		self.chan.wake_reader();
+		consumed
+	}
+	/// Flush and then clone the flushed value into the new writing cup so the writer keeps working from the latest state.
+	/// Costs a [`Clone::clone_from`] of `T` on top of [`flush`](Self::flush), which only swaps cups.
+	#[cfg(not(loom))]
+	pub fn flush_preserving(&mut self) -> bool
+	where
+		T: Clone,
+	{
+		let consumed = self.flush();
+		self.sync();
+		consumed
+	}
+	/// Clone the last flushed value into the writing cup
+	#[cfg(not(loom))]
+	fn sync(&mut self)
+	where
+		T: Clone,
+	{
+		// The published cup can't become the writing cup until the writer flushes again, so it is fine to read it alongside the reader
+		let published = unsafe { &*self.chan.cups[self.published_cup()].get() };
+		(**self).clone_from(published);
+	}
+	/// Bring the writing cup up to date with the last flushed value and then modify it with `f`, without flushing.
+	/// Costs a [`Clone::clone_from`] of `T`, use [`modify_unsynced`](Self::modify_unsynced) if `f` overwrites everything it depends on.
+	#[cfg(not(loom))]
+	pub fn modify<F: FnOnce(&mut T)>(&mut self, f: F)
+	where
+		T: Clone,
+	{
+		self.sync();
+		f(self);
+	}
+	/// Modify the writing cup with `f` without flushing or syncing it.
+	/// The writing cup holds whatever was flushed from it last, which is older than the latest flush.
+	pub fn modify_unsynced<F: FnOnce(&mut T)>(&mut self, f: F) {
+		#[cfg(not(loom))]
+		f(self);
+		#[cfg(loom)]
+		self.chan.cups[self.current_cup].with_mut(|cup| f(unsafe { &mut *cup }));
+	}
+	/// Index of the cup holding the most recently flushed value, which stays the same until the next flush.
+	/// The reader may be reading it too, so it must only be read.
+	#[cfg(not(loom))]
+	fn published_cup(&self) -> usize {
+		self.chan.published_cup()
+	}
+	/// The most recently flushed value (or the initial value if there was none).
+	/// The reader may be reading the same cup, but it can't be written to again until the next flush, which the borrow of `self` rules out.
+	#[cfg(not(loom))]
+	pub fn last_flushed(&self) -> &T {
+		unsafe { &*self.chan.cups[self.published_cup()].get() }
+	}
+	/// Borrow the last flushed value and the writing cup at the same time, to compute the next value from the previous one.
+	/// They are always different cups, the writing cup only becomes the published one after a flush.
+	/// ```rust
+	/// # use cupchan::cupchan;
+	/// let (mut writer, reader) = cupchan(1);
+	/// let (prev, next) = writer.split_borrow();
+	/// *next = *prev * 2;
+	/// writer.flush();
+	/// assert_eq!(*reader, 2);
+	/// ```
+	#[cfg(not(loom))]
+	pub fn split_borrow(&mut self) -> (&T, &mut T) {
+		let published = self.published_cup();
+		debug_assert_ne!(published, self.current_cup);
+		unsafe {
+			(
+				&*self.chan.cups[published].get(),
+				&mut *self.chan.cups[self.current_cup].get(),
+			)
+		}
+	}
+	/// Take down the channel from both of its endpoints and return the last flushed value.
+	/// Panics if `reader` belongs to a different channel.
+	#[cfg(not(loom))]
+	pub fn into_inner(self, reader: CupchanReader<T>) -> T {
+		assert!(
+			Arc::ptr_eq(&self.chan, &reader.chan),
+			"CupchanWriter::into_inner called with the reader of another channel"
+		);
+		let published = self.published_cup();
+		let chan = self.chan.clone();
+		drop(self);
+		drop(reader);
+		// Both endpoints are gone and they are the only other owners of the channel
+		let chan = Arc::into_inner(chan).unwrap();
+		let cup = chan.cups.into_iter().nth(published).unwrap();
+		cup.0.into_inner()
 	}
-	pub fn new_reader(&self) -> Option<CupchanReader<T>> {
-		// Set unconnected false, If was actually unconnected, return new reader
-		if self.chan.unconnected.swap(false, Ordering::SeqCst) {
-			Some(CupchanReader::new(self.chan))
+	/// Flush only if the writing cup differs from the last flushed value, returns whether a flush happened.
+	/// Costs a full [`PartialEq`] comparison with [`last_flushed`](Self::last_flushed) on every call, which is only worth it if flushing identical values wakes up work on the reader side.
+	#[cfg(not(loom))]
+	pub fn flush_if_changed(&mut self) -> bool
+	where
+		T: PartialEq,
+	{
+		if **self == *self.last_flushed() {
+			return false;
+		}
+		self.flush();
+		true
+	}
+	/// Replace the value in the writing cup without reading what was left in it
+	pub fn write(&mut self, value: T) {
+		#[cfg(not(loom))]
+		unsafe {
+			*self.chan.cups[self.current_cup].get() = value;
+		}
+		#[cfg(loom)]
+		self.chan.cups[self.current_cup].with_mut(|cup| unsafe { *cup = value });
+	}
+	/// [`write`](Self::write) and [`flush`](Self::flush) in one call, returns the same as [`flush`](Self::flush).
+	pub fn write_and_flush(&mut self, value: T) -> bool {
+		self.write(value);
+		self.flush()
+	}
+	/// Flush `value` and get back what was left in the writing cup, e.g. to reuse its allocation for the next value.
+	pub fn replace(&mut self, value: T) -> T {
+		#[cfg(not(loom))]
+		let old = mem::replace(&mut **self, value);
+		#[cfg(loom)]
+		let old = self.chan.cups[self.current_cup]
+			.with_mut(|cup| mem::replace(unsafe { &mut *cup }, value));
+		self.flush();
+		old
+	}
+	/// Modify the writing cup with `f` and flush, returns the same as [`flush`](Self::flush).
+	/// Note that the writing cup holds whatever was flushed from it last, not necessarily the latest value.
+	pub fn update<F: FnOnce(&mut T)>(&mut self, f: F) -> bool {
+		self.modify_unsynced(f);
+		self.flush()
+	}
+	/// Get a guard to the writing cup that flushes when dropped
+	/// ```rust
+	/// # use cupchan::cupchan;
+	/// let (mut writer, reader) = cupchan(0);
+	/// *writer.write_guard() = 5;
+	/// assert_eq!(*reader, 5);
+	/// ```
+	pub fn write_guard(&mut self) -> WriteGuard<'_, T> {
+		WriteGuard { writer: self }
+	}
+	/// Overwrite every cup with `value` and reset the channel to its initial state, giving a clean slate for the next [`new_reader`](Self::new_reader).
+	/// Can only be done once the reader has been dropped, otherwise returns [`ReconnectError::StillConnected`].
+	/// A reader that only [`disconnect`](CupchanReader::disconnect)ed may still be borrowing its cup, so it counts as connected here.
+	///
+	/// A new reader can only be created through this writer, and `&mut self` makes sure that can't happen before the reset is done.
+	/// The reset is then published to the new reader by the `SeqCst` compare-exchange in `new_reader`.
+	#[cfg(not(loom))]
+	pub fn reset(&mut self, value: T) -> Result<(), ReconnectError>
+	where
+		T: Clone,
+	{
+		// Acquire the reader's last accesses to its cup, released by its drop
+		if self.chan.reader_state.load(Ordering::Acquire) != DROPPED {
+			return Err(ReconnectError::StillConnected);
+		}
+		for cup in &self.chan.cups[1..] {
+			unsafe { *cup.get() = value.clone() };
+		}
+		unsafe { *self.chan.cups[0].get() = value };
+		self.chan.generation.store(0, Ordering::Relaxed);
+		self.chan.dropped.store(0, Ordering::Relaxed);
+		for generation in self.chan.cup_generations.iter() {
+			generation.store(0, Ordering::Relaxed);
+		}
+		self.chan
+			.state
+			.store(OBJECT_PERMUTATIONS[0], Ordering::Release);
+		self.current_cup = WRITER_CUP_MAP[OBJECT_PERMUTATIONS[0]];
+		Ok(())
+	}
+	/// Number of times this channel has been flushed.
+	/// Incremented by a separate store in [`flush`](Self::flush) before the cups are swapped, so it only ever grows between resets.
+	pub fn generation(&self) -> usize {
+		self.chan.generation.load(Ordering::Relaxed)
+	}
+	/// Returns true if the reader has swapped in the last flushed value.
+	/// Advisory only, the reader may catch up right after this returns false.
+	pub fn reader_caught_up(&self) -> bool {
+		self.chan.state.load(Ordering::Acquire) & UPDATE_FLAG == 0
+	}
+	/// Returns true if a flushed value is still waiting for the reader, the writer side counterpart of [`CupchanReader::changed`].
+	/// Opposite of [`reader_caught_up`](Self::reader_caught_up), e.g. to keep coalescing updates before flushing again.
+	#[inline]
+	pub fn has_pending(&self) -> bool {
+		!self.reader_caught_up()
+	}
+	/// Decoded permutation state, for debugging. Only a snapshot, the reader may swap cups right after
+	pub fn debug_state(&self) -> DebugState {
+		self.chan.raw_state()
+	}
+	/// Number of flushes that overwrote a value the reader never read
+	pub fn dropped_count(&self) -> usize {
+		self.chan.dropped.load(Ordering::Relaxed)
+	}
+	/// Returns true if the reader hasn't been dropped or disconnected.
+	/// May race with a concurrent drop of the reader, but will become false eventually.
+	pub fn is_reader_connected(&self) -> bool {
+		self.chan.reader_state.load(Ordering::Acquire) == ALIVE
+	}
+	/// Returns true if the reader hasn't been dropped, unlike [`is_reader_connected`](Self::is_reader_connected) this stays true after it [`disconnect`](CupchanReader::disconnect)s.
+	#[inline]
+	pub fn is_reader_alive(&self) -> bool {
+		self.reader_state() != PeerState::Dropped
+	}
+	/// Whether the reader is connected, disconnected but still around, or dropped
+	pub fn reader_state(&self) -> PeerState {
+		PeerState::load(&self.chan.reader_state)
+	}
+	/// Create a new reader if the previous one was dropped.
+	/// Its first read returns the last flushed value (or the initial value if there was none), never an older cup.
+	pub fn new_reader(&self) -> Result<CupchanReader<T>, ReconnectError> {
+		if Cupchan::<T>::reconnect(&self.chan.reader_state) {
+			Ok(CupchanReader::new(self.chan.clone()))
 		} else {
-			None
+			Err(ReconnectError::StillConnected)
 		}
 	}
-
+	/// Tell the reader that this writer is done without dropping it, as if it had been dropped.
+	/// The reader can't create a new writer until this one is actually dropped, flushes still go through.
+	pub fn disconnect(&self) {
+		self.close();
+		self.chan
+			.writer_state
+			.store(DISCONNECTED, Ordering::Release);
+	}
+	/// Wake up everything waiting on the reader side so it can observe the disconnect
+	fn close(&self) {
+		let waiter = self.chan.waiter.swap(CLOSED_WAITER, Ordering::AcqRel);
+		// Already closed if disconnect() was called before
+		#[cfg(feature = "std")]
+		if !waiter.is_null() && waiter != CLOSED_WAITER {
+			unsafe { Box::from_raw(waiter) }.unpark();
+		}
+		#[cfg(not(feature = "std"))]
+		debug_assert!(waiter.is_null() || waiter == CLOSED_WAITER); // Nothing can park without std
+															  // Not the load gated wake_stream(), no RMW on `state` orders it against a registration here
+		#[cfg(feature = "async")]
+		self.chan.stream_waker.wake();
+	}
+	/// Raw pointer to the writing cup, for writing in place from FFI or DMA.
+	/// Only valid until the next flush, after which the cup may be read by the reader.
+	#[cfg(not(loom))]
+	pub fn cup_mut_ptr(&mut self) -> *mut T {
+		self.chan.cups[self.current_cup].get()
+	}
 	#[cfg(loom)]
-	pub fn loom_ptr(&mut self) -> MutPtr<T> {
-		self.current_cup.get_mut()
+	pub fn loom_ptr(&mut self) -> LoomWritePtr<'_, T> {
+		LoomWritePtr {
+			ptr: self.chan.cups[self.current_cup].get_mut(),
+			_writer: core::marker::PhantomData,
+		}
+	}
+}
+/// Tracked pointer to the writing cup under loom, borrows the writer like [`DerefMut`] does so it can't be held across a flush
+#[cfg(loom)]
+#[derive(Debug)]
+pub struct LoomWritePtr<'a, T> {
+	ptr: MutPtr<T>,
+	_writer: core::marker::PhantomData<&'a mut CupchanWriter<T>>,
+}
+#[cfg(loom)]
+impl<T> LoomWritePtr<'_, T> {
+	/// Takes `&mut self` so two mutable references to the cup can't be alive at once.
+	///
+	/// # Safety
+	/// Same as [`MutPtr::deref`]
+	pub unsafe fn deref(&mut self) -> &mut T {
+		self.ptr.deref()
 	}
 }
 #[cfg(not(loom))]
 impl<T> Deref for CupchanWriter<T> {
 	type Target = T;
 	fn deref(&self) -> &Self::Target {
-		unsafe { &*self.current_cup.get() }
+		unsafe { &*self.chan.cups[self.current_cup].get() }
 	}
 }
 #[cfg(not(loom))]
 impl<T> DerefMut for CupchanWriter<T> {
 	fn deref_mut(&mut self) -> &mut Self::Target {
-		unsafe { &mut *self.current_cup.get() }
+		unsafe { &mut *self.chan.cups[self.current_cup].get() }
+	}
+}
+/// Shows the writing cup and which cup is which, without touching the channel
+impl<T: fmt::Debug> fmt::Debug for CupchanWriter<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut f = f.debug_struct("CupchanWriter");
+		#[cfg(not(loom))]
+		f.field("value", &**self);
+		f.field("state", &self.debug_state()).finish()
 	}
 }
 impl<T> Drop for CupchanWriter<T> {
 	fn drop(&mut self) {
-		// Set unconnected to true
-		let was_unconnected = self.chan.unconnected.swap(true, Ordering::AcqRel);
-		if was_unconnected {
-			// If was unconnected, drop channel
-			unsafe {
-				let ptr = std::mem::transmute::<_, *mut Cupchan<T>>(self.chan);
-				ptr::drop_in_place(ptr);
-			}
-		}
+		self.close();
+		// Allow a new writer to be created
+		self.chan.writer_state.store(DROPPED, Ordering::Release);
 	}
 }
 // Allow sending between threads
 unsafe impl<T: Sync + Send> Send for CupchanWriter<T> {}
 unsafe impl<T: Sync + Send> Sync for CupchanWriter<T> {}
 
+/// Mutable access to the writing cup, flushes the channel when dropped
+#[derive(Debug)]
+pub struct WriteGuard<'a, T> {
+	writer: &'a mut CupchanWriter<T>,
+}
+#[cfg(not(loom))]
+impl<T> Deref for WriteGuard<'_, T> {
+	type Target = T;
+	fn deref(&self) -> &Self::Target {
+		self.writer
+	}
+}
+#[cfg(not(loom))]
+impl<T> DerefMut for WriteGuard<'_, T> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		self.writer
+	}
+}
+impl<T> Drop for WriteGuard<'_, T> {
+	fn drop(&mut self) {
+		self.writer.flush();
+	}
+}
+
+/// Shared access to one reading cup, created by [`CupchanReader::borrow`]
+#[cfg(not(loom))]
+#[derive(Debug)]
+pub struct ReadGuard<'a, T> {
+	cup: &'a T,
+	/// Keeps the reader from swapping out the cup while the guard is alive
+	_reader: PhantomData<&'a mut CupchanReader<T>>,
+}
+#[cfg(not(loom))]
+impl<T> Deref for ReadGuard<'_, T> {
+	type Target = T;
+	#[inline]
+	fn deref(&self) -> &Self::Target {
+		self.cup
+	}
+}
+#[cfg(not(loom))]
+impl<T: AsRef<U>, U: ?Sized> AsRef<U> for ReadGuard<'_, T> {
+	fn as_ref(&self) -> &U {
+		self.cup.as_ref()
+	}
+}
+/// Only the guard implements [`Borrow`](core::borrow::Borrow), it keeps returning the same value while the reader swaps cups on every access.
+#[cfg(not(loom))]
+impl<T> core::borrow::Borrow<T> for ReadGuard<'_, T> {
+	fn borrow(&self) -> &T {
+		self.cup
+	}
+}
+
 // when created, modify state to set reader lock flag
 // when dropped, modify state permutation to swap reader & storage object, unset reader lock flag, unset storage new flag
 /// Read from the Cup Channel by dereferencing this obejct
-
-#[derive(Debug)]
-pub struct CupchanReader<T: 'static> {
-	chan: &'static Cupchan<T>,
+/// Dereferencing swaps in the latest flushed value, use [`peek`](Self::peek) to look at the current value again without swapping.
+///
+/// Not `Clone`: the three cups are one for the writer, one for storage and one for *the* reader, and a read swaps the reader cup with storage.
+/// A second reader would hand the cup the first one is still reading from back to the writer, use [`cupchan_spmc`] or [`cupchan_broadcast`] to fan out instead.
+pub struct CupchanReader<T> {
+	chan: Arc<Cupchan<T>>,
 }
 impl<T> CupchanReader<T> {
-	fn new(chan: &'static Cupchan<T>) -> Self {
+	fn new(chan: Arc<Cupchan<T>>) -> Self {
+		// No cup index is cached, every read locates the reading cup from an Acquire load of `state`.
+		// Either the update flag is set and the read swaps in the last flush, or the reading cup already holds it.
 		Self { chan }
 	}
 	#[inline]
-	fn read(&self) -> &'static UnsafeCell<T> {
+	fn read(&self) -> &UnsafeCell<T> {
+		&self.chan.cups[self.read_index()]
+	}
+	/// Swap in the latest value if there is one and return the index of the reading cup
+	#[inline]
+	fn read_index(&self) -> usize {
+		// Skip the RMW if there is nothing new, only the reader can move the reading cup
+		let state = self.chan.state.load(Ordering::Acquire);
+		if state & UPDATE_FLAG == 0 {
+			return READER_CUP_MAP[state];
+		}
+		// Acquire pairs with the Release in flush so the new cup's contents are visible, Release hands the old reading cup back to the writer
 		let res = self
 			.chan
 			.state
@@ -238,16 +878,260 @@ impl<T> CupchanReader<T> {
 				Some(state ^ READER_STATE_MAP[state])
 			})
 			.unwrap();
-		&self.chan.cups[READER_CUP_MAP[res ^ READER_STATE_MAP[res]]]
+		#[cfg(feature = "async")]
+		if res & UPDATE_FLAG != 0 {
+			// Only a load unless a FlushFuture is pending, the swap above orders it against the future registering
+			self.chan.writer_waker.wake_registered();
+		}
+		READER_CUP_MAP[res ^ READER_STATE_MAP[res]]
+	}
+	/// Number of times the writer has flushed
+	pub fn generation(&self) -> usize {
+		self.chan.generation.load(Ordering::Acquire)
+	}
+	/// Decoded permutation state, for debugging. Only a snapshot, the reader may swap cups right after
+	pub fn debug_state(&self) -> DebugState {
+		self.chan.raw_state()
+	}
+	/// Number of flushed values that were overwritten before the reader got to them
+	pub fn dropped_count(&self) -> usize {
+		self.chan.dropped.load(Ordering::Relaxed)
+	}
+	/// Read the latest value along with the generation it was flushed at (0 for the initial value).
+	/// Comparing generations between reads gives the number of flushes that were never read.
+	#[cfg(not(loom))]
+	pub fn read_versioned(&self) -> (&T, usize) {
+		let index = self.read_index();
+		let generation = self.chan.cup_generations[index].load(Ordering::Relaxed);
+		(unsafe { &*self.chan.cups[index].get() }, generation)
+	}
+	/// Returns true if the writer has flushed a value that hasn't been read yet.
+	/// Does not consume the update, the next deref will still swap it in.
+	pub fn changed(&self) -> bool {
+		self.chan.state.load(Ordering::Acquire) & UPDATE_FLAG != 0
+	}
+	/// Swap in the latest value once and get a guard to it, so repeated accesses don't each do an atomic RMW.
+	/// The guard pins the reader to one cup and won't observe flushes until it is dropped and the reader is borrowed again.
+	/// ```rust
+	/// # use cupchan::cupchan;
+	/// let (mut writer, mut reader) = cupchan((0, 0));
+	/// *writer = (1, 2);
+	/// writer.flush();
+	/// let guard = reader.borrow();
+	/// assert_eq!(guard.0 + guard.1, 3);
+	/// ```
+	#[cfg(not(loom))]
+	pub fn borrow(&mut self) -> ReadGuard<'_, T> {
+		ReadGuard {
+			cup: unsafe { &*self.read().get() },
+			_reader: PhantomData,
+		}
+	}
+	/// Same as [`borrow`](Self::borrow), named after `tokio::sync::watch::Receiver::borrow_and_update` to ease porting.
+	/// The counterpart of watch's `borrow` is [`peek`](Self::peek), except that it can't see a flush before it is swapped in.
+	#[cfg(not(loom))]
+	#[inline]
+	pub fn borrow_and_update(&mut self) -> ReadGuard<'_, T> {
+		self.borrow()
+	}
+	/// Returns the value obtained by the last read (deref) without swapping in newer data.
+	/// Only does an atomic load, so it will not observe a flush that happened after the last read.
+	#[cfg(not(loom))]
+	pub fn peek(&self) -> &T {
+		let state = self.chan.state.load(Ordering::Acquire);
+		unsafe { &*self.chan.cups[READER_CUP_MAP[state]].get() }
+	}
+	/// Returns the new value if the writer has flushed since the last read, otherwise returns None without touching the state
+	#[cfg(not(loom))]
+	pub fn try_read(&self) -> Option<&T> {
+		// Only the reader clears the flag so the deref is guaranteed to swap in the new value
+		self.changed().then(|| &**self)
+	}
+	/// Read the latest value and return an owned clone of it, unaffected by later flushes.
+	/// Use this to modify the value locally, the reading cup itself can't be borrowed mutably as it stays the writer's [`last_flushed`](CupchanWriter::last_flushed) until the next flush.
+	#[cfg(not(loom))]
+	#[doc(alias = "recv_cloned")]
+	pub fn snapshot(&self) -> T
+	where
+		T: Clone,
+	{
+		(**self).clone()
+	}
+	/// Non-blocking counterpart of `recv`, same as [`try_read`](Self::try_read).
+	#[cfg(not(loom))]
+	#[inline]
+	pub fn try_recv(&self) -> Option<&T> {
+		self.try_read()
+	}
+	/// Same as [`try_recv`](Self::try_recv) but returns a clone of the new value, like `mpsc`/`crossbeam` receivers do.
+	/// Costs one atomic RMW if there is a new value and only a load otherwise.
+	#[cfg(not(loom))]
+	pub fn try_recv_cloned(&self) -> Option<T>
+	where
+		T: Clone,
+	{
+		self.try_read().cloned()
+	}
+	/// Same as [`changed`](Self::changed), pure observation of the storage-ready flag.
+	#[inline]
+	pub fn has_update(&self) -> bool {
+		self.changed()
+	}
+	/// Block the current thread until the writer flushes a new value and return it.
+	/// The writer must use [`CupchanWriter::flush_notify`] to wake up the reader.
+	/// Returns [`Disconnected`] if the writer is dropped while there is no unread value.
+	///
+	/// Takes `&mut self` because only one thread at a time can be registered to be woken up.
+	#[cfg(all(not(loom), feature = "std"))]
+	pub fn recv(&mut self) -> Result<&T, Disconnected> {
+		self.recv_until(None).map_err(|_| Disconnected)
+	}
+	/// Like [`recv`](Self::recv) but gives up with [`RecvTimeoutError::Timeout`] if nothing is flushed within `timeout`.
+	#[cfg(all(not(loom), feature = "std"))]
+	pub fn recv_timeout(&mut self, timeout: Duration) -> Result<&T, RecvTimeoutError> {
+		self.recv_until(Some(Instant::now() + timeout))
 	}
-	pub fn new_writer(&self) -> Option<CupchanWriter<T>> {
-		// Set unconnected false, If was actually unconnected, return new reader
-		if self.chan.unconnected.swap(false, Ordering::SeqCst) {
-			Some(CupchanWriter::new(self.chan))
+	#[cfg(all(not(loom), feature = "std"))]
+	fn recv_until(&mut self, deadline: Option<Instant>) -> Result<&T, RecvTimeoutError> {
+		loop {
+			if self.changed() {
+				return Ok(self);
+			}
+			if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+				return Err(RecvTimeoutError::Timeout);
+			}
+			// Register this thread so flush() can unpark it
+			let waiter = Box::into_raw(Box::new(thread::current()));
+			if let Err(current) = self.chan.waiter.compare_exchange(
+				ptr::null_mut(),
+				waiter,
+				Ordering::AcqRel,
+				Ordering::Acquire,
+			) {
+				drop(unsafe { Box::from_raw(waiter) });
+				// `&mut self` keeps other threads from registering, so only the writer's close() can have filled the slot
+				debug_assert_eq!(current, CLOSED_WAITER);
+				return Err(RecvTimeoutError::Disconnected);
+			}
+			// Check again with an RMW so it is ordered against a concurrent flush()
+			if self.chan.state.fetch_or(0, Ordering::AcqRel) & UPDATE_FLAG == 0 {
+				// Spurious wakeups and timeouts are sorted out by the next iteration
+				match deadline {
+					Some(deadline) => {
+						thread::park_timeout(deadline.saturating_duration_since(Instant::now()))
+					}
+					None => thread::park(),
+				}
+			}
+			// Reclaim the handle if the writer didn't take it
+			if self
+				.chan
+				.waiter
+				.compare_exchange(waiter, ptr::null_mut(), Ordering::AcqRel, Ordering::Acquire)
+				.is_ok()
+			{
+				drop(unsafe { Box::from_raw(waiter) });
+			}
+		}
+	}
+	/// Like [`recv`](Self::recv) but woken by a plain [`CupchanWriter::flush`] as well.
+	/// Once this has been called, every flush checks whether the reader is parked (channels that never call wait don't pay for this).
+	#[cfg(all(not(loom), feature = "std"))]
+	pub fn wait(&mut self) -> Result<&T, Disconnected> {
+		// Set before registering in recv() so a flush ordered after the registration observes it
+		self.chan.wait_used.store(true, Ordering::Relaxed);
+		self.recv()
+	}
+	/// Like [`wait`](Self::wait) but gives up with [`RecvTimeoutError::Timeout`] if nothing is flushed within `timeout`,
+	/// same as [`recv_timeout`](Self::recv_timeout) apart from being woken by a plain flush.
+	#[cfg(all(not(loom), feature = "std"))]
+	pub fn wait_timeout(&mut self, timeout: Duration) -> Result<&T, RecvTimeoutError> {
+		self.chan.wait_used.store(true, Ordering::Relaxed);
+		self.recv_timeout(timeout)
+	}
+	/// Iterate over clones of each new value the writer flushes, spinning until one is available.
+	/// Ends once the writer is dropped.
+	#[cfg(all(not(loom), feature = "std"))]
+	pub fn updates(&self) -> Updates<'_, T> {
+		Updates { reader: self }
+	}
+	/// Returns true if the writer hasn't been dropped or disconnected.
+	/// May race with a concurrent drop of the writer, but will become false eventually.
+	pub fn is_writer_connected(&self) -> bool {
+		self.chan.writer_state.load(Ordering::Acquire) == ALIVE
+	}
+	/// Returns true if the writer hasn't been dropped, unlike [`is_writer_connected`](Self::is_writer_connected) this stays true after it [`disconnect`](CupchanWriter::disconnect)s.
+	#[inline]
+	pub fn is_writer_alive(&self) -> bool {
+		self.writer_state() != PeerState::Dropped
+	}
+	/// Whether the writer is connected, disconnected but still around, or dropped
+	pub fn writer_state(&self) -> PeerState {
+		PeerState::load(&self.chan.writer_state)
+	}
+	/// Create a new writer if the previous one was dropped
+	pub fn new_writer(&self) -> Result<CupchanWriter<T>, ReconnectError> {
+		if Cupchan::<T>::reconnect(&self.chan.writer_state) {
+			Ok(CupchanWriter::new(self.chan.clone()))
 		} else {
-			None
+			Err(ReconnectError::StillConnected)
+		}
+	}
+	/// Consume the reader and return the latest flushed value.
+	/// If the writer is already gone this was the last handle, the value is moved out and the channel is torn down.
+	/// Otherwise the value is cloned and the reader is dropped as usual, letting the writer create a new one.
+	/// ```rust
+	/// # use cupchan::cupchan;
+	/// let (mut writer, reader) = cupchan(String::new());
+	/// writer.write_and_flush("final".into());
+	/// drop(writer);
+	/// assert_eq!(reader.into_inner(), "final");
+	/// ```
+	#[cfg(not(loom))]
+	pub fn into_inner(self) -> T
+	where
+		T: Clone,
+	{
+		self.take().unwrap_or_else(|reader| reader.snapshot())
+	}
+	/// Move the latest flushed value out of the channel if the writer has been dropped, otherwise give the reader back.
+	/// A writer that only [`disconnect`](CupchanWriter::disconnect)ed still owns its cup, so this fails until it is dropped.
+	/// ```rust
+	/// # use cupchan::cupchan;
+	/// let (mut writer, reader) = cupchan(vec![0]);
+	/// writer.write_and_flush(vec![1]);
+	/// let reader = reader.take().unwrap_err();
+	/// drop(writer);
+	/// assert_eq!(reader.take().unwrap(), [1]);
+	/// ```
+	#[cfg(not(loom))]
+	pub fn take(self) -> Result<T, Self> {
+		// Take out the Arc without running Drop, the reader is rebuilt if the writer is still around
+		let chan = unsafe { ptr::read(&mem::ManuallyDrop::new(self).chan) };
+		match Arc::try_unwrap(chan) {
+			// The writer may have flushed again before it was dropped
+			Ok(chan) => {
+				let published = chan.published_cup();
+				let cup = chan.cups.into_iter().nth(published).unwrap();
+				Ok(cup.0.into_inner())
+			}
+			Err(chan) => Err(Self::new(chan)),
 		}
 	}
+	/// Tell the writer that this reader is done without dropping it, as if it had been dropped.
+	/// The writer can't create a new reader until this one is actually dropped, reading still works.
+	pub fn disconnect(&self) {
+		// An RMW so a FlushFuture registering concurrently either sees the disconnect or is seen by the wake below
+		self.chan.reader_state.swap(DISCONNECTED, Ordering::AcqRel);
+		#[cfg(feature = "async")]
+		self.chan.writer_waker.wake_registered();
+	}
+	/// Swap in the latest value and get a raw pointer to it.
+	/// Only valid for reading until the next read, which may hand the cup back to the writer.
+	#[cfg(not(loom))]
+	pub fn cup_ptr(&self) -> *const T {
+		self.read().get()
+	}
 	#[cfg(loom)]
 	pub fn loom_ptr(&self) -> ConstPtr<T> {
 		self.read().get()
@@ -260,31 +1144,257 @@ impl<T> Deref for CupchanReader<T> {
 		unsafe { &(*self.read().get()) }
 	}
 }
+/// Forwards to the latest value like [`Deref`], so every call swaps in a new flush if there is one.
+/// Use [`CupchanReader::borrow`] to stay on one value.
+#[cfg(not(loom))]
+impl<T: AsRef<U>, U: ?Sized> AsRef<U> for CupchanReader<T> {
+	fn as_ref(&self) -> &U {
+		(**self).as_ref()
+	}
+}
+/// Compares the value returned by [`peek`](CupchanReader::peek), so a comparison never swaps cups.
+/// A flush that hasn't been read yet isn't compared, deref the reader first to check the latest value.
+/// ```rust
+/// # use cupchan::cupchan;
+/// let (mut writer, reader) = cupchan(0);
+/// writer.write_and_flush(1);
+/// assert_eq!(reader, 0);
+/// assert_eq!(*reader, 1);
+/// assert_eq!(reader, 1);
+/// ```
+#[cfg(not(loom))]
+impl<T: PartialEq> PartialEq<T> for CupchanReader<T> {
+	fn eq(&self, other: &T) -> bool {
+		self.peek() == other
+	}
+}
+/// Shows the value of the last read like [`peek`](CupchanReader::peek), so formatting never swaps in a new flush
+impl<T: fmt::Debug> fmt::Debug for CupchanReader<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut f = f.debug_struct("CupchanReader");
+		#[cfg(not(loom))]
+		f.field("value", self.peek());
+		f.field("state", &self.debug_state()).finish()
+	}
+}
 impl<T> Drop for CupchanReader<T> {
 	fn drop(&mut self) {
-		// Set unconnected to true
-		let was_unconnected = self.chan.unconnected.swap(true, Ordering::AcqRel);
-		if was_unconnected {
-			// If was unconnected, drop channel
-			unsafe {
-				let ptr = std::mem::transmute::<_, *mut Cupchan<T>>(self.chan);
-				ptr::drop_in_place(ptr);
-			}
-		}
+		// Allow a new reader to be created, Release pairs with the Acquire in reset() and new_reader().
+		// An RMW for the same reason as in disconnect()
+		self.chan.reader_state.swap(DROPPED, Ordering::AcqRel);
+		// Resolve a pending FlushFuture, after setting the state so it observes the disconnect
+		#[cfg(feature = "async")]
+		self.chan.writer_waker.wake_registered();
 	}
 }
 // Allow sending between threads
 unsafe impl<T: Sync + Send> Send for CupchanReader<T> {}
 unsafe impl<T: Sync + Send> Sync for CupchanReader<T> {}
 
-#[cfg(test)]
+/// Iterator over updates to the channel, created by [`CupchanReader::updates`]
+#[cfg(all(not(loom), feature = "std"))]
+#[derive(Debug)]
+pub struct Updates<'a, T> {
+	reader: &'a CupchanReader<T>,
+}
+#[cfg(all(not(loom), feature = "std"))]
+impl<T: Clone> Iterator for Updates<'_, T> {
+	type Item = T;
+	fn next(&mut self) -> Option<T> {
+		loop {
+			if self.reader.changed() {
+				return Some((**self.reader).clone());
+			}
+			// Not is_writer_connected(), the writer may have disconnected without being dropped yet
+			if self.reader.chan.writer_dropped() {
+				// Writer may have flushed right before dropping
+				return self.reader.changed().then(|| (**self.reader).clone());
+			}
+			thread::yield_now();
+		}
+	}
+}
+
+/// Returned when the other end of the channel has been dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Disconnected;
+impl fmt::Display for Disconnected {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("cup channel is disconnected")
+	}
+}
+impl Error for Disconnected {}
+
+/// Returned by [`CupchanReader::recv_timeout`]
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+	/// Nothing was flushed before the timeout
+	Timeout,
+	/// The writer was dropped while there was no unread value
+	Disconnected,
+}
+#[cfg(feature = "std")]
+impl fmt::Display for RecvTimeoutError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RecvTimeoutError::Timeout => f.write_str("timed out waiting on cup channel"),
+			RecvTimeoutError::Disconnected => Disconnected.fmt(f),
+		}
+	}
+}
+#[cfg(feature = "std")]
+impl Error for RecvTimeoutError {}
+
+/// Returned by [`CupchanWriter::try_flush`] when the reader hasn't read the previous flush yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushBlocked;
+impl fmt::Display for FlushBlocked {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("previous flush hasn't been read yet")
+	}
+}
+impl Error for FlushBlocked {}
+
+/// Which cup is the writer's, reader's and storage, and whether storage holds an unread flush.
+/// A flush swaps the writer's and storage cups and sets `updated`, a read of an update swaps the reader's and storage cups and clears it.
+/// ```rust
+/// # use cupchan::cupchan;
+/// let (mut writer, reader) = cupchan(0);
+/// let before = writer.debug_state();
+/// writer.flush();
+/// let after = writer.debug_state();
+/// assert_eq!((after.writer, after.storage), (before.storage, before.writer));
+/// assert!(after.updated);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugState {
+	/// Index of the cup being written to
+	pub writer: usize,
+	/// Index of the cup being read from
+	pub reader: usize,
+	/// Index of the cup in between
+	pub storage: usize,
+	/// Storage holds a flush the reader hasn't swapped in yet
+	pub updated: bool,
+}
+
+/// State of the other end of the channel, returned by [`CupchanWriter::reader_state`] and [`CupchanReader::writer_state`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerState {
+	/// In use
+	Connected,
+	/// Called `disconnect()` but hasn't been dropped, so it can't be replaced yet
+	Disconnected,
+	/// Dropped, a new one can be created
+	Dropped,
+}
+impl PeerState {
+	fn load(state: &AtomicU8) -> Self {
+		match state.load(Ordering::Acquire) {
+			ALIVE => PeerState::Connected,
+			DISCONNECTED => PeerState::Disconnected,
+			_ => PeerState::Dropped,
+		}
+	}
+}
+
+/// Returned by [`CupchanWriter::new_reader`] and [`CupchanReader::new_writer`] when an endpoint can't be created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconnectError {
+	/// The other end of the channel hasn't been dropped
+	StillConnected,
+}
+impl fmt::Display for ReconnectError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ReconnectError::StillConnected => {
+				f.write_str("other end of the cup channel is still connected")
+			}
+		}
+	}
+}
+impl Error for ReconnectError {}
+
+// Loom models live in tests/loom_test.rs, these rely on Deref which loom builds leave out
+#[cfg(all(test, not(loom)))]
 mod tests {
+	#[cfg(feature = "nightly")]
 	extern crate test;
+	#[cfg(feature = "nightly")]
 	use test::Bencher;
 
 	use std::thread;
 
-	use crate::cupchan;
+	use crate::{
+		cupchan, cupchan_broadcast, cupchan_n, cupchan_spmc, cupchan_with, CupchanReader,
+		CupchanWriter, FlushBlocked, PeerState, ReconnectError,
+	};
+
+	/// Waker that counts how often it is woken, for checking when the async apis wake
+	#[cfg(feature = "async")]
+	struct CountWaker(std::sync::atomic::AtomicUsize);
+	#[cfg(feature = "async")]
+	impl CountWaker {
+		/// The counter and a waker incrementing it
+		fn new() -> (std::sync::Arc<Self>, std::task::Waker) {
+			let count = std::sync::Arc::new(CountWaker(Default::default()));
+			(count.clone(), count.into())
+		}
+		fn count(&self) -> usize {
+			self.0.load(std::sync::atomic::Ordering::SeqCst)
+		}
+	}
+	#[cfg(feature = "async")]
+	impl std::task::Wake for CountWaker {
+		fn wake(self: std::sync::Arc<Self>) {
+			self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+		}
+	}
+
+	/// Walk every state reachable from the valid permutations and check each transition moves the right cups
+	#[test]
+	fn test_state_tables() {
+		use crate::{
+			OBJECT_PERMUTATIONS, READER_CUP_MAP, READER_STATE_MAP, UPDATE_FLAG, WRITER_CUP_MAP,
+			WRITER_STATE_MAP,
+		};
+		let cups = |state: usize| {
+			assert!(
+				OBJECT_PERMUTATIONS.contains(&(state & !UPDATE_FLAG)),
+				"invalid state {state:#b}"
+			);
+			let (writer, reader) = (WRITER_CUP_MAP[state], READER_CUP_MAP[state]);
+			assert!(writer < 3 && reader < 3 && writer != reader);
+			(writer, reader, 3 - writer - reader)
+		};
+		let mut seen = Vec::new();
+		let mut todo = OBJECT_PERMUTATIONS
+			.iter()
+			.flat_map(|&state| [state, state | UPDATE_FLAG])
+			.collect::<Vec<_>>();
+		while let Some(state) = todo.pop() {
+			if seen.contains(&state) {
+				continue;
+			}
+			seen.push(state);
+			let (writer, reader, storage) = cups(state);
+
+			let written = state ^ WRITER_STATE_MAP[state];
+			assert_eq!(cups(written), (storage, reader, writer));
+			assert!(written & UPDATE_FLAG != 0);
+
+			let read = state ^ READER_STATE_MAP[state];
+			if state & UPDATE_FLAG != 0 {
+				assert_eq!(cups(read), (writer, storage, reader));
+				assert!(read & UPDATE_FLAG == 0);
+			} else {
+				assert_eq!(read, state);
+			}
+			todo.extend([written, read]);
+		}
+		assert_eq!(seen.len(), 12);
+	}
 
 	#[test]
 	fn test_chan_sync() {
@@ -297,6 +1407,10 @@ mod tests {
 		writer.flush();
 		assert_eq!(*reader, 2);
 
+		assert_eq!(
+			writer.new_reader().err(),
+			Some(ReconnectError::StillConnected)
+		);
 		drop(reader);
 
 		let reader = writer.new_reader().unwrap();
@@ -307,15 +1421,1314 @@ mod tests {
 		drop(writer)
 	}
 
-	const MAX: usize = 5_000;
 	#[test]
-	fn cupchan_async_greedy_reader() {
-		let (mut writer, reader) = cupchan(0usize);
+	fn test_changed() {
+		let (mut writer, reader) = cupchan(0);
+		assert!(!reader.changed());
+		*writer = 1;
+		writer.flush();
+		assert!(reader.changed());
+		assert!(reader.changed()); // Checking doesn't consume the update
+		assert!(reader.has_update());
+		assert_eq!(*reader, 1);
+		assert!(!reader.changed());
+		assert!(!reader.has_update());
+	}
 
-		let join = thread::spawn(move || {
-			for i in 0..MAX {
-				*writer = i;
-				writer.flush();
+	#[test]
+	fn test_try_read() {
+		let (mut writer, reader) = cupchan(0);
+		assert_eq!(reader.try_read(), None);
+		*writer = 1;
+		writer.flush();
+		assert_eq!(reader.try_read(), Some(&1));
+		assert_eq!(reader.try_read(), None);
+		assert_eq!(*reader, 1);
+	}
+
+	#[test]
+	fn test_borrow() {
+		let (mut writer, mut reader) = cupchan([0; 4]);
+		writer.write_and_flush([1, 2, 3, 4]);
+		{
+			let guard = reader.borrow();
+			writer.write_and_flush([5; 4]);
+			// The guard stays on the cup it swapped in
+			assert_eq!(guard.iter().sum::<i32>(), 10);
+			assert_eq!(guard[0], 1);
+			assert_eq!(guard[3], 4);
+		}
+		assert_eq!(*reader.borrow(), [5; 4]);
+		writer.write_and_flush([6; 4]);
+		assert_eq!(*reader.peek(), [5; 4]);
+		assert!(reader.changed());
+		assert_eq!(*reader.borrow_and_update(), [6; 4]);
+		assert!(!reader.changed());
+	}
+
+	#[test]
+	fn test_as_ref() {
+		fn len(s: impl AsRef<str>) -> usize {
+			s.as_ref().len()
+		}
+		let (mut writer, mut reader) = cupchan(String::from("a"));
+		assert_eq!(len(&reader), 1);
+		writer.write_and_flush("abc".into());
+		assert_eq!(len(reader.borrow()), 3);
+		writer.write_and_flush("abcd".into());
+		assert_eq!(len(&reader), 4);
+	}
+
+	#[test]
+	fn test_map() {
+		let (mut writer, reader) = cupchan((0, vec![0u8; 16]));
+		let reader = reader.map(|(a, b)| a + b.len());
+		assert_eq!(reader.read(), 16);
+		assert_eq!(reader.try_read(), None);
+		writer.0 = 1;
+		writer.flush();
+		assert!(reader.changed());
+		assert_eq!(reader.try_read(), Some(17));
+		assert!(!reader.changed());
+		writer.write_and_flush((1, vec![]));
+		assert_eq!(reader.read(), 1);
+		drop(writer);
+		assert!(!reader.is_writer_connected());
+		assert_eq!(*reader.into_inner(), (1, vec![]));
+	}
+
+	#[test]
+	fn test_endpoint_debug() {
+		let (mut writer, reader) = cupchan(0);
+		writer.write_and_flush(1);
+		*writer = 2;
+		let state = writer.debug_state();
+		assert!(state.updated);
+		assert_eq!(
+			format!("{writer:?}"),
+			format!("CupchanWriter {{ value: 2, state: {state:?} }}")
+		);
+		// Formatting the reader doesn't consume the flush
+		assert_eq!(
+			format!("{reader:?}"),
+			format!("CupchanReader {{ value: 0, state: {state:?} }}")
+		);
+		assert!(reader.changed());
+	}
+
+	#[test]
+	fn test_partial_eq() {
+		let (mut writer, reader) = cupchan(vec![1]);
+		assert_eq!(reader, vec![1]);
+		writer.write_and_flush(vec![2]);
+		// Comparing doesn't consume the update
+		assert_ne!(reader, vec![2]);
+		assert!(reader.changed());
+		assert_eq!(reader.try_read(), Some(&vec![2]));
+		assert_eq!(reader, vec![2]);
+	}
+
+	#[test]
+	fn test_map_ref() {
+		#[derive(Clone)]
+		struct State {
+			a: u32,
+			b: Vec<u8>,
+		}
+		let (mut writer, reader) = cupchan(State {
+			a: 0,
+			b: vec![0; 64],
+		});
+		let reader = reader.map_ref(|state| &state.a);
+		assert_eq!(*reader, 0);
+		writer.a = 1;
+		writer.flush();
+		assert!(reader.changed());
+		assert_eq!(*reader, 1);
+		writer.write_and_flush(State { a: 2, b: vec![] });
+		assert_eq!(*reader, 2);
+		assert!(reader.into_inner().b.is_empty());
+	}
+
+	#[test]
+	fn test_raw_handle() {
+		let (mut writer, reader) = cupchan(0);
+		let handle = reader.into_raw();
+		assert!(writer.is_reader_connected());
+		writer.write_and_flush(1);
+		let mut out = 0;
+		unsafe { crate::cupchan_read::<i32>(handle, &mut out) };
+		assert_eq!(out, 1);
+		let reader = unsafe { CupchanReader::<i32>::from_raw(handle) };
+		assert_eq!(*reader, 1);
+		drop(reader);
+		assert!(!writer.is_reader_connected());
+	}
+
+	#[test]
+	fn test_try_recv() {
+		let (mut writer, reader) = cupchan(0);
+		assert_eq!(reader.try_recv(), None);
+		*writer = 1;
+		writer.flush();
+		assert_eq!(reader.try_recv(), Some(&1));
+		assert_eq!(reader.try_recv(), None);
+	}
+
+	#[test]
+	fn test_try_recv_cloned() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		assert_eq!(reader.try_recv_cloned(), None);
+		writer.write_and_flush(vec![1]);
+		assert_eq!(reader.try_recv_cloned(), Some(vec![1]));
+		assert_eq!(reader.try_recv_cloned(), None);
+	}
+
+	#[test]
+	fn test_snapshot() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		*writer = vec![1];
+		writer.flush();
+		let snapshot = reader.snapshot();
+		assert_eq!(snapshot, *reader);
+		*writer = vec![2];
+		writer.flush();
+		assert_eq!(*reader, [2]);
+		assert_eq!(snapshot, [1]);
+	}
+
+	#[test]
+	fn test_generation() {
+		let (mut writer, reader) = cupchan(0);
+		assert_eq!(reader.read_versioned(), (&0, 0));
+		for i in 1..=10 {
+			*writer = i;
+			writer.flush();
+		}
+		assert_eq!(reader.generation(), 10);
+		assert_eq!(writer.generation(), 10);
+		assert_eq!(reader.read_versioned(), (&10, 10));
+		*writer = 11;
+		writer.flush();
+		assert_eq!(reader.read_versioned(), (&11, 11));
+		assert_eq!(reader.read_versioned(), (&11, 11));
+	}
+
+	#[test]
+	fn test_try_flush() {
+		let (mut writer, reader) = cupchan(0);
+		writer.write(1);
+		assert_eq!(writer.try_flush(), Ok(()));
+		writer.write(2);
+		assert_eq!(writer.try_flush(), Err(FlushBlocked));
+		assert_eq!(*writer, 2);
+		assert_eq!(writer.generation(), 1);
+		assert_eq!(*reader, 1);
+		assert_eq!(writer.try_flush(), Ok(()));
+		assert_eq!(*reader, 2);
+		assert_eq!(writer.dropped_count(), 0);
+	}
+
+	#[test]
+	fn test_reader_caught_up() {
+		let (mut writer, reader) = cupchan(0);
+		assert!(writer.reader_caught_up());
+		assert!(!writer.has_pending());
+		writer.flush();
+		assert!(!writer.reader_caught_up());
+		assert!(writer.has_pending());
+		let _ = *reader;
+		assert!(writer.reader_caught_up());
+		assert!(!writer.has_pending());
+	}
+
+	#[test]
+	fn test_dropped_count() {
+		let (mut writer, reader) = cupchan(0);
+		for i in 1..=5 {
+			*writer = i;
+			writer.flush();
+		}
+		// Only the last of the 5 flushes is read
+		assert_eq!(writer.dropped_count(), 4);
+		assert_eq!(*reader, 5);
+		*writer = 6;
+		writer.flush();
+		assert_eq!(*reader, 6);
+		assert_eq!(reader.dropped_count(), 4);
+	}
+
+	#[test]
+	fn test_debug_state() {
+		let (mut writer, reader) = cupchan(0);
+		let state = writer.debug_state();
+		assert!(!state.updated);
+		for i in 0..20 {
+			if i % 3 == 0 {
+				let _ = *reader;
+			} else {
+				writer.flush();
+			}
+			let state = reader.debug_state();
+			let mut cups = [state.writer, state.reader, state.storage];
+			cups.sort();
+			assert_eq!(cups, [0, 1, 2]);
+			assert_eq!(state.updated, reader.changed());
+			assert_eq!(state, writer.debug_state());
+		}
+	}
+
+	#[test]
+	fn test_debug_state_rotation() {
+		let (mut writer, reader) = cupchan(0);
+		// Initial <W><S><R> permutation
+		let mut state = writer.debug_state();
+		assert_eq!(
+			state,
+			crate::DebugState {
+				writer: 0,
+				storage: 1,
+				reader: 2,
+				updated: false
+			}
+		);
+		for i in 0..12 {
+			let next = if i % 3 == 2 {
+				let _ = *reader;
+				reader.debug_state()
+			} else {
+				writer.flush();
+				writer.debug_state()
+			};
+			let expected = if i % 3 == 2 {
+				// Reading swaps reader and storage if there was an update
+				crate::DebugState {
+					reader: state.storage,
+					storage: state.reader,
+					updated: false,
+					..state
+				}
+			} else {
+				// Flushing swaps writer and storage
+				crate::DebugState {
+					writer: state.storage,
+					storage: state.writer,
+					updated: true,
+					..state
+				}
+			};
+			assert_eq!(next, expected);
+			state = next;
+		}
+	}
+
+	#[test]
+	fn test_peek() {
+		let (mut writer, reader) = cupchan(0);
+		*writer = 1;
+		writer.flush();
+		*writer = 2;
+		writer.flush();
+		let first = *reader;
+		assert_eq!(first, 2);
+		*writer = 3;
+		writer.flush();
+		for _ in 0..3 {
+			assert_eq!(*reader.peek(), first);
+		}
+		assert_eq!(*reader, 3);
+	}
+
+	#[test]
+	fn test_scoped_refs() {
+		let data = [1, 2, 3, 4];
+		let data = &data[..];
+		let (mut writer, reader) = cupchan(&data[..0]);
+		thread::scope(|s| {
+			s.spawn(move || {
+				for end in 1..=data.len() {
+					writer.write_and_flush(&data[..end]);
+				}
+			});
+			while reader.len() < data.len() {
+				thread::yield_now();
+			}
+		});
+		assert_eq!(*reader, data);
+	}
+
+	#[test]
+	fn test_chan_arc() {
+		let (mut writer, reader) = cupchan(vec![0u8]);
+		*writer = vec![1];
+		writer.flush();
+		assert_eq!(*reader, [1]);
+
+		drop(reader);
+		let reader = writer.new_reader().unwrap();
+		*writer = vec![2];
+		writer.flush();
+		drop(writer);
+		assert_eq!(*reader, [2]);
+	}
+
+	#[test]
+	fn test_spmc() {
+		let (mut writer, reader) = cupchan_spmc(0);
+		let joins = (0..3)
+			.map(|_| {
+				let reader = reader.clone();
+				thread::spawn(move || {
+					let mut last = 0;
+					while last != 1000 {
+						let value = reader.read();
+						assert!(value >= last);
+						last = value;
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+		for i in 1..=1000 {
+			*writer = i;
+			writer.flush();
+		}
+		for join in joins {
+			join.join().unwrap();
+		}
+		assert_eq!(reader.read(), 1000);
+		assert_eq!(reader.generation(), 1000);
+		drop(reader);
+		assert!(!writer.is_reader_connected());
+	}
+
+	#[test]
+	fn test_broadcast() {
+		let (mut writer, factory) = cupchan_broadcast(0);
+		let mut first = factory.subscribe();
+		assert_eq!(first.try_read(), Some(0));
+		assert_eq!(first.try_read(), None);
+		*writer = 1;
+		writer.flush();
+		let mut second = factory.subscribe();
+		assert!(first.changed());
+		assert_eq!(first.read(), 1);
+		assert!(!first.changed());
+		*writer = 2;
+		writer.flush();
+		assert_eq!(second.try_read(), Some(2));
+		assert_eq!(first.try_read(), Some(2));
+		assert_eq!(second.try_read(), None);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_serde_round_trip() {
+		let (mut writer, reader) = cupchan(vec![String::new()]);
+		writer.write_and_flush(vec!["a".into(), "b".into()]);
+		let mut json = Vec::new();
+		reader
+			.serialize_value(&mut serde_json::Serializer::new(&mut json))
+			.unwrap();
+		assert_eq!(json, br#"["a","b"]"#);
+
+		let (mut other, other_reader) = cupchan(Vec::<String>::new());
+		assert!(other
+			.deserialize_into(&mut serde_json::Deserializer::from_slice(&json))
+			.unwrap());
+		assert_eq!(*other_reader, *reader);
+		assert!(other
+			.deserialize_into(&mut serde_json::Deserializer::from_slice(b"[1]"))
+			.is_err());
+		assert!(!other_reader.changed());
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_recv() {
+		let (mut writer, mut reader) = cupchan(0);
+		let join = thread::spawn(move || {
+			for i in 1..=3 {
+				thread::sleep(std::time::Duration::from_millis(10));
+				*writer = i;
+				writer.flush_notify();
+			}
+		});
+		let mut current = 0;
+		while let Ok(&value) = reader.recv() {
+			assert!(value > current);
+			current = value;
+		}
+		assert_eq!(current, 3);
+		assert_eq!(reader.recv(), Err(crate::Disconnected));
+		join.join().unwrap();
+	}
+
+	#[test]
+	fn test_chan_with() {
+		struct NotClone(usize);
+		let mut count = 0;
+		let (mut writer, reader) = cupchan_with(|| {
+			count += 1;
+			NotClone(count)
+		});
+		assert_eq!(count, 3);
+		assert_eq!(writer.0, 1); // Writer starts on the first cup
+		writer.0 = 4;
+		writer.flush();
+		assert_eq!(reader.0, 4);
+	}
+
+	#[test]
+	fn test_update() {
+		let (mut writer, reader) = cupchan(Vec::<i32>::new());
+		writer.update(|v| v.push(1));
+		assert_eq!(*reader, [1]);
+		writer.update(|v| v.extend([2, 3]));
+		assert_eq!(*reader, [2, 3]); // Each cup has its own vec
+	}
+
+	#[test]
+	fn test_flush_preserving() {
+		let (mut writer, reader) = cupchan(0);
+		for i in 1..=5 {
+			*writer += 1;
+			writer.flush_preserving();
+			assert_eq!(*reader, i);
+		}
+		assert_eq!(*writer, 5);
+	}
+
+	#[test]
+	fn test_write_guard() {
+		let (mut writer, reader) = cupchan(0);
+		{
+			let mut guard = writer.write_guard();
+			*guard = 1;
+			*guard += 1;
+			assert!(!reader.changed()); // Not flushed until guard is dropped
+		}
+		assert!(reader.changed());
+		assert_eq!(*reader, 2);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_stream() {
+		use futures_core::Stream;
+		use std::{
+			pin::Pin,
+			task::{Context, Poll},
+		};
+		let (count, waker) = CountWaker::new();
+		let mut cx = Context::from_waker(&waker);
+
+		let (mut writer, reader) = cupchan(0);
+		let mut stream = reader.into_stream();
+		assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+		*writer = 1;
+		writer.flush();
+		*writer = 2;
+		writer.flush();
+		let woken = count.count();
+		assert!(woken > 0);
+		assert_eq!(
+			Pin::new(&mut stream).poll_next(&mut cx),
+			Poll::Ready(Some(2))
+		);
+		assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+		drop(writer);
+		assert!(count.count() > woken);
+		assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+	}
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_stream_last_value() {
+		use futures_core::Stream;
+		use std::{
+			pin::Pin,
+			task::{Context, Poll, Waker},
+		};
+		let mut cx = Context::from_waker(Waker::noop());
+		let (mut writer, reader) = cupchan(0);
+		let mut stream = reader.into_stream();
+		for i in 1..=5 {
+			*writer = i;
+			writer.flush();
+		}
+		drop(writer);
+		// Flushes are coalesced and the last one is still yielded after the writer is dropped
+		assert_eq!(
+			Pin::new(&mut stream).poll_next(&mut cx),
+			Poll::Ready(Some(5))
+		);
+		assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_flush_async() {
+		use std::{
+			future::Future,
+			pin::pin,
+			task::{Context, Poll, Waker},
+		};
+		let mut cx = Context::from_waker(Waker::noop());
+		let (mut writer, reader) = cupchan(0);
+		{
+			*writer = 1;
+			let mut flush = pin!(writer.flush_async());
+			assert_eq!(flush.as_mut().poll(&mut cx), Poll::Pending);
+			assert_eq!(*reader, 1);
+			assert_eq!(flush.as_mut().poll(&mut cx), Poll::Ready(()));
+		}
+		*writer = 2;
+		let mut flush = pin!(writer.flush_async());
+		assert_eq!(flush.as_mut().poll(&mut cx), Poll::Pending);
+		drop(reader);
+		assert_eq!(flush.as_mut().poll(&mut cx), Poll::Ready(()));
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_changed_async() {
+		use std::{
+			future::Future,
+			pin::pin,
+			task::{Context, Poll},
+		};
+		let (count, waker) = CountWaker::new();
+		let mut cx = Context::from_waker(&waker);
+
+		let (mut writer, mut reader) = cupchan(0);
+		{
+			let mut changed = pin!(reader.changed_async());
+			assert_eq!(changed.as_mut().poll(&mut cx), Poll::Pending);
+			writer.write_and_flush(1);
+			assert_eq!(count.count(), 1);
+			assert_eq!(changed.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+		}
+		assert_eq!(*reader, 1);
+
+		// A dropped future is deregistered
+		assert_eq!(pin!(reader.changed_async()).poll(&mut cx), Poll::Pending);
+		writer.write_and_flush(2);
+		assert_eq!(count.count(), 1);
+		assert_eq!(
+			pin!(reader.changed_async()).poll(&mut cx),
+			Poll::Ready(Ok(()))
+		);
+		assert_eq!(*reader, 2);
+
+		let mut changed = pin!(reader.changed_async());
+		assert_eq!(changed.as_mut().poll(&mut cx), Poll::Pending);
+		drop(writer);
+		assert_eq!(count.count(), 2);
+		assert_eq!(
+			changed.as_mut().poll(&mut cx),
+			Poll::Ready(Err(crate::Disconnected))
+		);
+	}
+
+	#[test]
+	fn test_cup_ptr() {
+		let (mut writer, reader) = cupchan([0u8; 16]);
+		let ptr = writer.cup_mut_ptr();
+		unsafe { ptr.cast::<u8>().write_bytes(7, 16) };
+		writer.flush();
+		assert_eq!(unsafe { *reader.cup_ptr() }, [7; 16]);
+		assert_eq!(reader.cup_ptr(), reader.peek() as *const _);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_poll_changed() {
+		use std::task::{Context, Poll, Waker};
+		let (mut writer, reader) = cupchan(0);
+		let mut cx = Context::from_waker(Waker::noop());
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Pending);
+		writer.write_and_flush(1);
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Ready(Ok(())));
+		// Not consumed until read
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Ready(Ok(())));
+		assert_eq!(*reader, 1);
+
+		let (count, waker) = CountWaker::new();
+		let mut cx = Context::from_waker(&waker);
+		// Polling again replaces the registration instead of piling up wakers
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Pending);
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Pending);
+		writer.write_and_flush(2);
+		assert_eq!(count.count(), 1);
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Ready(Ok(())));
+		assert_eq!(*reader, 2);
+
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Pending);
+		writer.disconnect();
+		assert_eq!(count.count(), 2);
+		assert_eq!(
+			reader.poll_changed(&mut cx),
+			Poll::Ready(Err(crate::Disconnected))
+		);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_poll_after_reader_disconnect() {
+		use std::task::{Context, Poll};
+		let (mut writer, reader) = cupchan(0);
+		let (count, waker) = CountWaker::new();
+		let mut cx = Context::from_waker(&waker);
+		reader.disconnect();
+		// A disconnected reader can still wait for flushes
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Pending);
+		writer.write_and_flush(1);
+		assert_eq!(count.count(), 1);
+		assert_eq!(reader.poll_changed(&mut cx), Poll::Ready(Ok(())));
+		assert_eq!(*reader, 1);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn test_register_waker() {
+		let (count, waker) = CountWaker::new();
+		let (mut writer, reader) = cupchan(0);
+		reader.register_waker(&waker);
+		writer.write_and_flush(1);
+		assert_eq!(count.count(), 1);
+		// Woken once per registration
+		writer.write_and_flush(2);
+		assert_eq!(count.count(), 1);
+		reader.register_waker(&waker);
+		drop(writer);
+		assert_eq!(count.count(), 2);
+	}
+
+	#[test]
+	fn test_write() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		writer.write(vec![1]);
+		assert_eq!(*writer, [1]);
+		assert_eq!(*reader, [0]);
+		writer.flush();
+		assert_eq!(*reader, [1]);
+		assert!(writer.write_and_flush(vec![2]));
+		assert!(!writer.write_and_flush(vec![3]));
+		assert_eq!(*reader, [3]);
+	}
+
+	#[test]
+	fn test_modify() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		for i in 1..=3 {
+			writer.modify(|v| v.push(i));
+			writer.flush();
+		}
+		assert_eq!(*reader, [0, 1, 2, 3]);
+		// Without syncing the writing cup still holds an older flush
+		writer.modify_unsynced(|v| v.push(4));
+		writer.flush();
+		assert_eq!(*reader, [0, 1, 2, 4]);
+	}
+
+	#[test]
+	fn test_split_borrow() {
+		let (mut writer, reader) = cupchan(0);
+		for i in 1..=10 {
+			let (prev, next) = writer.split_borrow();
+			*next = prev + i;
+			writer.flush();
+			if i % 3 == 0 {
+				let _ = &*reader;
+			}
+		}
+		assert_eq!(*writer.last_flushed(), 55);
+		assert_eq!(*reader, 55);
+	}
+
+	#[test]
+	fn test_last_flushed() {
+		let (mut writer, reader) = cupchan(0);
+		assert_eq!(*writer.last_flushed(), 0);
+		for i in 1..=3 {
+			writer.write_and_flush(i);
+			assert_eq!(*writer.last_flushed(), i);
+		}
+		assert_eq!(*reader, 3);
+		assert_eq!(*writer.last_flushed(), 3);
+		writer.write(4);
+		assert_eq!(*writer.last_flushed(), 3);
+	}
+
+	#[test]
+	fn test_replace() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		let mut buf = writer.replace(vec![1]);
+		assert_eq!(buf, [0]);
+		assert_eq!(*reader, [1]);
+		for i in 2..=4 {
+			buf.clear();
+			buf.push(i);
+			buf = writer.replace(buf);
+			assert_eq!(*reader, [i]);
+			// Never the cup the reader is holding
+			assert_ne!(buf.as_ptr(), reader.peek().as_ptr());
+		}
+	}
+
+	#[test]
+	fn test_into_inner() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		writer.write_and_flush(vec![1]);
+		writer.write(vec![2]);
+		assert_eq!(writer.into_inner(reader), [1]);
+	}
+
+	#[test]
+	fn test_reader_into_inner() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		writer.write_and_flush(vec![1]);
+		drop(writer);
+		assert_eq!(reader.into_inner(), [1]);
+
+		// Flushed right before the writer was dropped, without being read
+		let (mut writer, reader) = cupchan(vec![0]);
+		let _ = &*reader;
+		writer.write_and_flush(vec![2]);
+		drop(writer);
+		assert_eq!(reader.into_inner(), [2]);
+
+		// With the writer still alive the value is cloned and the writer can reconnect
+		let (mut writer, reader) = cupchan(vec![0]);
+		writer.write_and_flush(vec![3]);
+		assert_eq!(reader.into_inner(), [3]);
+		assert!(!writer.is_reader_connected());
+		let reader = writer.new_reader().unwrap();
+		assert_eq!(*reader, [3]);
+	}
+
+	#[test]
+	fn test_take() {
+		let (mut writer, reader) = cupchan(vec![0]);
+		writer.write_and_flush(vec![1]);
+		writer.disconnect();
+		let reader = reader.take().unwrap_err();
+		// Failing to take doesn't disturb the channel
+		assert!(reader.changed());
+		writer.write_and_flush(vec![2]);
+		drop(writer);
+		assert_eq!(reader.take().unwrap(), [2]);
+	}
+
+	#[test]
+	#[should_panic(expected = "reader of another channel")]
+	fn test_into_inner_other_channel() {
+		let (writer, _reader) = cupchan(0);
+		let (_writer, reader) = cupchan(0);
+		writer.into_inner(reader);
+	}
+
+	#[test]
+	fn test_flush_if_changed() {
+		let (mut writer, reader) = cupchan(0);
+		assert!(!writer.flush_if_changed());
+		*writer = 1;
+		assert!(writer.flush_if_changed());
+		*writer = 1;
+		assert!(!writer.flush_if_changed());
+		assert_eq!(writer.generation(), 1);
+		assert_eq!(*reader, 1);
+		*writer = 1;
+		assert!(!writer.flush_if_changed());
+		*writer = 2;
+		assert!(writer.flush_if_changed());
+		assert_eq!(*reader, 2);
+	}
+
+	#[test]
+	fn test_flush_consumed() {
+		let (mut writer, reader) = cupchan(0);
+		assert!(writer.flush());
+		assert!(!writer.flush()); // Previous flush wasn't read
+		assert_eq!(*reader, 0);
+		*writer = 1;
+		assert!(writer.flush());
+		assert_eq!(*reader, 1);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_updates() {
+		let (mut writer, reader) = cupchan(0usize);
+		let join = thread::spawn(move || {
+			for i in 1..=MAX {
+				*writer = i;
+				writer.flush();
+			}
+		});
+		let mut current = 0;
+		for value in reader.updates() {
+			assert!(value > current);
+			current = value;
+		}
+		assert_eq!(current, MAX);
+		join.join().unwrap();
+
+		// Disconnecting the reader doesn't end the iterator while the writer keeps flushing
+		let (mut writer, reader) = cupchan(0usize);
+		reader.disconnect();
+		let join = thread::spawn(move || {
+			for i in 1..=3 {
+				thread::sleep(std::time::Duration::from_millis(10));
+				writer.write_and_flush(i);
+			}
+		});
+		assert_eq!(reader.updates().last(), Some(3));
+		join.join().unwrap();
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_recv_timeout() {
+		use std::time::Duration;
+		let (mut writer, mut reader) = cupchan(0);
+		let join = thread::spawn(move || {
+			thread::sleep(Duration::from_millis(100));
+			writer.write(1);
+			writer.flush_notify();
+		});
+		assert_eq!(
+			reader.recv_timeout(Duration::from_millis(10)),
+			Err(crate::RecvTimeoutError::Timeout)
+		);
+		assert_eq!(reader.recv_timeout(Duration::from_secs(10)), Ok(&1));
+		join.join().unwrap();
+		assert_eq!(
+			reader.recv_timeout(Duration::from_secs(10)),
+			Err(crate::RecvTimeoutError::Disconnected)
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_wait_timeout() {
+		use std::time::{Duration, Instant};
+		let (mut writer, mut reader) = cupchan(0);
+		assert_eq!(
+			reader.wait_timeout(Duration::from_millis(10)),
+			Err(crate::RecvTimeoutError::Timeout)
+		);
+		let join = thread::spawn(move || {
+			thread::sleep(Duration::from_millis(50));
+			writer.write_and_flush(1);
+		});
+		let start = Instant::now();
+		assert_eq!(reader.wait_timeout(Duration::from_secs(10)), Ok(&1));
+		assert!(start.elapsed() < Duration::from_secs(5));
+		join.join().unwrap();
+		assert_eq!(
+			reader.wait_timeout(Duration::from_secs(10)),
+			Err(crate::RecvTimeoutError::Disconnected)
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_wait() {
+		let (mut writer, mut reader) = cupchan(0);
+		let join = thread::spawn(move || {
+			for i in 1..=3 {
+				thread::sleep(std::time::Duration::from_millis(10));
+				*writer = i;
+				writer.flush();
+			}
+		});
+		let mut current = 0;
+		while let Ok(&value) = reader.wait() {
+			assert!(value > current);
+			current = value;
+		}
+		assert_eq!(current, 3);
+		join.join().unwrap();
+	}
+
+	#[test]
+	fn test_reset() {
+		let (mut writer, reader) = cupchan(0);
+		for i in 1..=4 {
+			*writer = i;
+			writer.flush();
+		}
+		assert_eq!(writer.reset(10), Err(ReconnectError::StillConnected));
+		drop(reader);
+		writer.reset(10).unwrap();
+		assert_eq!(*writer, 10);
+		let reader = writer.new_reader().unwrap();
+		assert!(!reader.changed());
+		assert_eq!(reader.read_versioned(), (&10, 0));
+	}
+
+	#[test]
+	fn test_reconnect_fresh() {
+		let (mut writer, mut reader) = cupchan(0);
+		assert_eq!(*reader, 0);
+		for i in 1..=200 {
+			let join = thread::spawn(move || {
+				let value = *reader;
+				assert!(value == i - 1 || value == i);
+			});
+			// Keep flushing while the old reader is still around
+			loop {
+				writer.write_and_flush(i);
+				if let Ok(new) = writer.new_reader() {
+					reader = new;
+					break;
+				}
+				thread::yield_now();
+			}
+			assert_eq!(*reader, i);
+			join.join().unwrap();
+		}
+	}
+
+	#[test]
+	fn test_connected() {
+		let (writer, reader) = cupchan(0);
+		assert!(writer.is_reader_connected());
+		assert!(reader.is_writer_connected());
+		drop(writer);
+		assert!(!reader.is_writer_connected());
+		let writer = reader.new_writer().unwrap();
+		assert!(reader.is_writer_connected());
+		drop(reader);
+		assert!(!writer.is_reader_connected());
+	}
+
+	#[test]
+	fn test_never_flushed() {
+		let (writer, reader) = cupchan(0);
+		drop(writer);
+		assert!(!reader.is_writer_connected());
+		assert_eq!(reader.generation(), 0);
+
+		let (mut writer, reader) = cupchan(0);
+		writer.write_and_flush(1);
+		drop(writer);
+		assert_eq!(reader.generation(), 1);
+	}
+
+	#[test]
+	fn test_alive() {
+		let (writer, reader) = cupchan(0);
+		assert!(writer.is_reader_alive());
+		drop(reader);
+		assert!(!writer.is_reader_alive());
+		let reader = writer.new_reader().unwrap();
+		assert!(writer.is_reader_alive());
+		drop(writer);
+		assert!(!reader.is_writer_alive());
+		let writer = reader.new_writer().unwrap();
+		assert!(reader.is_writer_alive());
+		assert!(writer.is_reader_alive());
+
+		reader.disconnect();
+		assert!(!writer.is_reader_connected());
+		assert!(writer.is_reader_alive());
+		assert_eq!(writer.reader_state(), PeerState::Disconnected);
+		drop(reader);
+		assert_eq!(writer.reader_state(), PeerState::Dropped);
+		let reader = writer.new_reader().unwrap();
+		assert_eq!(writer.reader_state(), PeerState::Connected);
+		writer.disconnect();
+		assert!(reader.is_writer_alive());
+		assert_eq!(reader.writer_state(), PeerState::Disconnected);
+	}
+
+	#[test]
+	fn test_disconnect() {
+		let (mut writer, reader) = cupchan(0);
+		reader.disconnect();
+		assert!(!writer.is_reader_connected());
+		// The reader still exists, so it can't be replaced yet
+		assert!(writer.new_reader().is_err());
+		writer.write_and_flush(1);
+		assert_eq!(*reader, 1);
+		drop(reader);
+		#[cfg_attr(not(feature = "std"), allow(unused_mut))]
+		let mut reader = writer.new_reader().unwrap();
+		assert!(writer.is_reader_connected());
+
+		writer.disconnect();
+		assert!(!reader.is_writer_connected());
+		assert!(reader.new_writer().is_err());
+		#[cfg(feature = "std")]
+		assert_eq!(reader.recv(), Err(crate::Disconnected));
+		#[cfg(feature = "std")]
+		writer.flush_notify();
+		drop(writer);
+		let writer = reader.new_writer().unwrap();
+		assert!(reader.is_writer_connected());
+		assert!(writer.is_reader_connected());
+	}
+
+	#[test]
+	fn test_reset_after_disconnect() {
+		let (mut writer, reader) = cupchan(vec![1]);
+		let borrowed = &*reader;
+		reader.disconnect();
+		// The reader is still alive and borrowing its cup
+		assert_eq!(writer.reset(vec![2]), Err(ReconnectError::StillConnected));
+		assert_eq!(borrowed, &[1]);
+		drop(reader);
+		writer.reset(vec![2]).unwrap();
+		assert_eq!(*writer.new_reader().unwrap(), [2]);
+	}
+
+	#[cfg(feature = "nightly")]
+	#[test]
+	fn test_try_cupchan() {
+		let (mut writer, reader) = crate::try_cupchan(vec![0]).unwrap();
+		writer.write_and_flush(vec![1]);
+		assert_eq!(*reader, [1]);
+	}
+
+	#[test]
+	fn test_chan_with_buffers() {
+		let (mut writer, reader) = cupchan_with(|| Vec::<u8>::with_capacity(1024));
+		assert_ne!(writer.as_ptr(), reader.as_ptr()); // Each cup has its own buffer
+		writer.extend_from_slice(b"hello");
+		writer.flush();
+		assert_eq!(&reader[..], b"hello");
+		assert_eq!(reader.capacity(), 1024);
+	}
+
+	#[test]
+	fn test_chan_n() {
+		let (mut writer, reader) = cupchan_n::<_, 5>(0);
+		for i in 1..=3 {
+			*writer = i;
+			writer.flush();
+		}
+		assert_eq!(reader.unread(), 3);
+		assert_eq!([*reader, *reader, *reader, *reader], [1, 2, 3, 3]);
+
+		// Oldest values are dropped once the writer gets 3 ahead
+		for i in 4..=8 {
+			*writer = i;
+			writer.flush();
+		}
+		assert_eq!(reader.unread(), 3);
+		assert_eq!([*reader, *reader, *reader, *reader], [6, 7, 8, 8]);
+	}
+	/// Run a pseudo random sequence of flushes and reads against a `VecDeque` of the unread values
+	fn check_chan_n_model<const N: usize>(seed: u64) {
+		use std::collections::VecDeque;
+		let (mut writer, reader) = cupchan_n::<_, N>(0);
+		// cupchan behaves like cupchan_n with 3 cups
+		let (mut writer3, reader3) = cupchan(0);
+		let mut unread = VecDeque::new();
+		let mut current = 0;
+		let mut rng = seed;
+		for value in 1..=1000 {
+			// xorshift64
+			rng ^= rng << 13;
+			rng ^= rng >> 7;
+			rng ^= rng << 17;
+			if rng & 1 == 0 {
+				*writer = value;
+				writer.flush();
+				if unread.len() == N - 2 {
+					unread.pop_front();
+				}
+				unread.push_back(value);
+				if N == 3 {
+					*writer3 = value;
+					writer3.flush();
+				}
+			} else {
+				assert_eq!(reader.unread(), unread.len());
+				current = unread.pop_front().unwrap_or(current);
+				assert_eq!(*reader, current, "N = {N}, seed = {seed}");
+				if N == 3 {
+					assert_eq!(*reader3, current);
+				}
+			}
+		}
+	}
+	#[test]
+	fn test_chan_n_model() {
+		for seed in 1..=16 {
+			check_chan_n_model::<3>(seed);
+			check_chan_n_model::<4>(seed);
+			check_chan_n_model::<5>(seed);
+			check_chan_n_model::<8>(seed);
+			check_chan_n_model::<12>(seed);
+		}
+	}
+	#[test]
+	fn cupchan_copy_async() {
+		let (mut writer, reader) = crate::cupchan_copy(0usize);
+
+		let join = thread::spawn(move || {
+			for i in 0..MAX {
+				*writer = i;
+				writer.flush();
+			}
+		});
+
+		let mut current = reader.read();
+		while current < MAX - 1 {
+			let read = reader.read();
+			assert!(current <= read && read < MAX);
+			current = read;
+		}
+
+		join.join().unwrap();
+	}
+	#[test]
+	fn cupchan_n_async() {
+		let (mut writer, reader) = cupchan_n::<_, 8>(0usize);
+
+		let join = thread::spawn(move || {
+			for i in 0..MAX {
+				*writer = i;
+				writer.flush();
+			}
+		});
+
+		let mut current = *reader;
+		while current < MAX - 1 {
+			let next = *reader;
+			assert!(next >= current);
+			current = next;
+		}
+
+		join.join().unwrap();
+	}
+
+	#[test]
+	fn test_chan_freed() {
+		use std::sync::atomic::{AtomicUsize, Ordering};
+		static DROPS: AtomicUsize = AtomicUsize::new(0);
+		struct Counted;
+		impl Drop for Counted {
+			fn drop(&mut self) {
+				DROPS.fetch_add(1, Ordering::SeqCst);
+			}
+		}
+		let (writer, reader) = cupchan_with(|| Counted);
+		drop(writer);
+		let writer = reader.new_writer().unwrap();
+		drop(reader);
+		assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+		drop(writer);
+		assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+	}
+
+	#[test]
+	fn test_cups_dropped_once() {
+		use std::sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc,
+		};
+		#[derive(Clone)]
+		struct Counted(Arc<AtomicUsize>);
+		impl Drop for Counted {
+			fn drop(&mut self) {
+				self.0.fetch_add(1, Ordering::SeqCst);
+			}
+		}
+		let check = |end: fn(CupchanWriter<Counted>, CupchanReader<Counted>)| {
+			let drops = Arc::new(AtomicUsize::new(0));
+			let (mut writer, reader) = cupchan(Counted(drops.clone()));
+			// Flushing and reading moves the cups around but never drops them
+			writer.flush();
+			let _ = &*reader;
+			end(writer, reader);
+			assert_eq!(drops.load(Ordering::SeqCst), 3);
+		};
+		check(|writer, reader| {
+			drop(writer);
+			drop(reader);
+		});
+		check(|writer, reader| {
+			drop(reader);
+			drop(writer);
+		});
+		check(|writer, reader| {
+			drop(reader);
+			let reader = writer.new_reader().unwrap();
+			drop(writer);
+			let writer = reader.new_writer().unwrap();
+			drop(reader);
+			drop(writer);
+		});
+		check(|writer, reader| {
+			let join = thread::spawn(move || drop(writer));
+			drop(reader);
+			join.join().unwrap();
+		});
+	}
+
+	#[test]
+	fn test_constructor_panic() {
+		use std::{
+			cell::Cell,
+			panic::{catch_unwind, AssertUnwindSafe},
+			rc::Rc,
+		};
+		/// Counts live instances and panics on the clone that brings `clones` to 0
+		struct Bomb {
+			live: Rc<Cell<usize>>,
+			clones: Rc<Cell<usize>>,
+		}
+		impl Bomb {
+			fn new(live: &Rc<Cell<usize>>, clones: &Rc<Cell<usize>>) -> Self {
+				live.set(live.get() + 1);
+				Bomb {
+					live: live.clone(),
+					clones: clones.clone(),
+				}
+			}
+		}
+		impl Clone for Bomb {
+			fn clone(&self) -> Self {
+				self.clones.set(self.clones.get() - 1);
+				assert_ne!(self.clones.get(), 0, "boom");
+				Bomb::new(&self.live, &self.clones)
+			}
+		}
+		impl Drop for Bomb {
+			fn drop(&mut self) {
+				self.live.set(self.live.get() - 1);
+			}
+		}
+		for n in 1..=2 {
+			let (live, clones) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(n)));
+			let initial = Bomb::new(&live, &clones);
+			assert!(catch_unwind(AssertUnwindSafe(|| cupchan(initial))).is_err());
+			assert_eq!(live.get(), 0);
+		}
+		for n in 1..=3 {
+			let (live, clones) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(n)));
+			let init = || {
+				clones.set(clones.get() - 1);
+				assert_ne!(clones.get(), 0, "boom");
+				Bomb::new(&live, &clones)
+			};
+			assert!(catch_unwind(AssertUnwindSafe(|| cupchan_with(init))).is_err());
+			assert_eq!(live.get(), 0);
+		}
+	}
+
+	const MAX: usize = 5_000;
+	#[test]
+	fn cupchan_async_greedy_reader() {
+		let (mut writer, reader) = cupchan(0usize);
+
+		let join = thread::spawn(move || {
+			for i in 0..MAX {
+				*writer = i;
+				writer.flush();
 			}
 		});
 
@@ -352,6 +2765,48 @@ mod tests {
 		join.join().unwrap();
 	}
 
+	#[test]
+	fn cupchan_copy_greedy_reader() {
+		let max = MAX as u64;
+		let (mut writer, reader) = crate::cupchan_copy(0u64);
+
+		let join = thread::spawn(move || {
+			for i in 0..max {
+				*writer = i;
+				writer.flush();
+			}
+		});
+
+		let mut current = reader.read();
+		while current < max - 1 {
+			current = reader.read();
+		}
+		assert!(reader.read() == max - 1);
+
+		join.join().unwrap();
+	}
+	#[test]
+	fn cupchan_copy_lazy_reader() {
+		let max = MAX as u64;
+		let (mut writer, reader) = crate::cupchan_copy(0u64);
+
+		let join = thread::spawn(move || {
+			for i in 0..max {
+				*writer = i;
+				writer.flush();
+			}
+		});
+
+		let mut current = reader.read();
+		while current < max - 1 {
+			thread::yield_now();
+			current = reader.read();
+		}
+		assert!(reader.read() == max - 1);
+
+		join.join().unwrap();
+	}
+
 	#[test]
 	fn crossbeam_chan_async() {
 		let (tx, rx) = crossbeam_channel::bounded(3);
@@ -409,12 +2864,71 @@ mod tests {
 		join.join().unwrap();
 	}
 
+	#[test]
+	fn std_mpsc_chan_async() {
+		let (tx, rx) = std::sync::mpsc::channel();
+
+		let join = thread::spawn(move || {
+			for i in 0..MAX {
+				tx.send(i).unwrap();
+			}
+		});
+
+		let mut current = 0;
+		for _ in 0..MAX {
+			current = rx.recv().unwrap();
+		}
+		assert!(current == MAX - 1);
+
+		join.join().unwrap();
+	}
+
+	#[test]
+	fn triple_buffer_greedy_reader() {
+		let (mut input, mut output) = triple_buffer::triple_buffer(&0usize);
+
+		let join = thread::spawn(move || {
+			for i in 0..MAX {
+				input.write(i);
+			}
+		});
+
+		let mut current = *output.read();
+		while current < MAX - 1 {
+			current = *output.read();
+		}
+		assert!(*output.read() == MAX - 1);
+
+		join.join().unwrap();
+	}
+	#[test]
+	fn triple_buffer_lazy_reader() {
+		let (mut input, mut output) = triple_buffer::triple_buffer(&0usize);
+
+		let join = thread::spawn(move || {
+			for i in 0..MAX {
+				input.write(i);
+			}
+		});
+
+		let mut current = *output.read();
+		while current < MAX - 1 {
+			thread::yield_now();
+			current = *output.read();
+		}
+		assert!(*output.read() == MAX - 1);
+
+		join.join().unwrap();
+	}
+
+	#[cfg(feature = "nightly")]
 	#[bench]
 	fn bench_cupchan_greedy(b: &mut Bencher) {
 		b.iter(|| {
 			cupchan_async_greedy_reader();
 		})
 	}
+	#[cfg(feature = "nightly")]
 	#[bench]
 	fn bench_cupchan_lazy(b: &mut Bencher) {
 		b.iter(|| {
@@ -422,6 +2936,25 @@ mod tests {
 		})
 	}
 
+	/// Same as the lazy benchmark with the atomic channel for `Copy` values
+	#[cfg(feature = "nightly")]
+	#[bench]
+	fn bench_cupchan_copy_lazy(b: &mut Bencher) {
+		b.iter(|| {
+			cupchan_copy_lazy_reader();
+		})
+	}
+
+	/// Same as the greedy benchmark with the atomic channel for `Copy` values
+	#[cfg(feature = "nightly")]
+	#[bench]
+	fn bench_cupchan_copy_greedy(b: &mut Bencher) {
+		b.iter(|| {
+			cupchan_copy_greedy_reader();
+		})
+	}
+
+	#[cfg(feature = "nightly")]
 	#[bench]
 	fn bench_crossbeam_chan_cap_3(b: &mut Bencher) {
 		b.iter(|| {
@@ -429,6 +2962,7 @@ mod tests {
 		})
 	}
 
+	#[cfg(feature = "nightly")]
 	#[bench]
 	fn bench_crossbeam_chan_cap_10(b: &mut Bencher) {
 		b.iter(|| {
@@ -436,10 +2970,35 @@ mod tests {
 		})
 	}
 
+	#[cfg(feature = "nightly")]
 	#[bench]
 	fn bench_flume_chan(b: &mut Bencher) {
 		b.iter(|| {
 			flume_chan_async();
 		})
 	}
+
+	#[cfg(feature = "nightly")]
+	#[bench]
+	fn bench_std_mpsc(b: &mut Bencher) {
+		b.iter(|| {
+			std_mpsc_chan_async();
+		})
+	}
+
+	#[cfg(feature = "nightly")]
+	#[bench]
+	fn bench_triple_buffer_greedy(b: &mut Bencher) {
+		b.iter(|| {
+			triple_buffer_greedy_reader();
+		})
+	}
+
+	#[cfg(feature = "nightly")]
+	#[bench]
+	fn bench_triple_buffer_lazy(b: &mut Bencher) {
+		b.iter(|| {
+			triple_buffer_lazy_reader();
+		})
+	}
 }
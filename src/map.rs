@@ -0,0 +1,124 @@
+//! Reader adapters that project the channel's value on every read
+
+#[cfg(not(loom))]
+use core::ops::Deref;
+use core::{fmt, marker::PhantomData};
+
+use crate::CupchanReader;
+
+impl<T> CupchanReader<T> {
+	/// Turn this reader into a [`MappedReader`] that applies `f` to the latest value on each read.
+	/// ```rust
+	/// use cupchan::cupchan;
+	///
+	/// let (mut writer, reader) = cupchan((0, String::from("big")));
+	/// let reader = reader.map(|(position, _)| *position);
+	/// writer.write_and_flush((1, String::new()));
+	/// assert_eq!(reader.read(), 1);
+	/// ```
+	pub fn map<U, F: Fn(&T) -> U>(self, f: F) -> MappedReader<T, U, F> {
+		MappedReader {
+			reader: self,
+			f,
+			_output: PhantomData,
+		}
+	}
+	/// Turn this reader into a [`MappedRefReader`] that dereferences to the part of the latest value selected by `f`.
+	/// Unlike [`map`](Self::map) nothing is copied out, `f` is applied again on every deref so new flushes show up.
+	/// ```rust
+	/// use cupchan::cupchan;
+	///
+	/// let (mut writer, reader) = cupchan((0, String::from("big")));
+	/// let reader = reader.map_ref(|(_, name)| name.as_str());
+	/// writer.write_and_flush((1, String::from("small")));
+	/// assert_eq!(&*reader, "small");
+	/// ```
+	pub fn map_ref<U: ?Sized, F: Fn(&T) -> &U>(self, f: F) -> MappedRefReader<T, U, F> {
+		MappedRefReader {
+			reader: self,
+			f,
+			_output: PhantomData,
+		}
+	}
+}
+
+/// Reader of a cup channel that returns a projection of the value, created by [`CupchanReader::map`]
+pub struct MappedReader<T, U, F> {
+	reader: CupchanReader<T>,
+	#[cfg_attr(loom, allow(dead_code))]
+	f: F,
+	_output: PhantomData<fn() -> U>,
+}
+impl<T, U, F: Fn(&T) -> U> MappedReader<T, U, F> {
+	/// Swap in the latest value like a deref of the reader would and apply the projection to it
+	#[cfg(not(loom))]
+	pub fn read(&self) -> U {
+		(self.f)(&self.reader)
+	}
+	/// Apply the projection to the new value if the writer has flushed since the last read, see [`CupchanReader::try_read`]
+	#[cfg(not(loom))]
+	pub fn try_read(&self) -> Option<U> {
+		self.reader.try_read().map(&self.f)
+	}
+}
+impl<T, U, F> MappedReader<T, U, F> {
+	/// Returns true if the writer has flushed a value that hasn't been read yet
+	pub fn changed(&self) -> bool {
+		self.reader.changed()
+	}
+	/// Returns true if the writer hasn't been dropped or disconnected.
+	pub fn is_writer_connected(&self) -> bool {
+		self.reader.is_writer_connected()
+	}
+	/// Number of times the writer has flushed
+	pub fn generation(&self) -> usize {
+		self.reader.generation()
+	}
+	/// Drop the projection and get the underlying reader back
+	pub fn into_inner(self) -> CupchanReader<T> {
+		self.reader
+	}
+}
+impl<T: fmt::Debug, U, F> fmt::Debug for MappedReader<T, U, F> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MappedReader")
+			.field("reader", &self.reader)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Reader of a cup channel that dereferences to a part of the value, created by [`CupchanReader::map_ref`]
+pub struct MappedRefReader<T, U: ?Sized, F> {
+	reader: CupchanReader<T>,
+	#[cfg_attr(loom, allow(dead_code))]
+	f: F,
+	_output: PhantomData<fn(&T) -> &U>,
+}
+#[cfg(not(loom))]
+impl<T, U: ?Sized, F: Fn(&T) -> &U> Deref for MappedRefReader<T, U, F> {
+	type Target = U;
+	fn deref(&self) -> &U {
+		(self.f)(&self.reader)
+	}
+}
+impl<T, U: ?Sized, F> MappedRefReader<T, U, F> {
+	/// Returns true if the writer has flushed a value that hasn't been read yet
+	pub fn changed(&self) -> bool {
+		self.reader.changed()
+	}
+	/// Returns true if the writer hasn't been dropped or disconnected.
+	pub fn is_writer_connected(&self) -> bool {
+		self.reader.is_writer_connected()
+	}
+	/// Drop the projection and get the underlying reader back
+	pub fn into_inner(self) -> CupchanReader<T> {
+		self.reader
+	}
+}
+impl<T: fmt::Debug, U: ?Sized, F> fmt::Debug for MappedRefReader<T, U, F> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MappedRefReader")
+			.field("reader", &self.reader)
+			.finish_non_exhaustive()
+	}
+}
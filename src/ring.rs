@@ -0,0 +1,192 @@
+//! Cup channel generalized to `N` cups, giving the reader a small queue of the last `N - 2` flushed values
+
+use core::fmt;
+
+use crate::{Arc, AtomicUsize, Ordering, UnsafeCell};
+#[cfg(loom)]
+use crate::{ConstPtr, MutPtr};
+#[cfg(not(loom))]
+use crate::{Deref, DerefMut};
+
+/// Shared state of a channel with `N` cups.
+///
+/// `state` packs a permutation of the cup indices into `N` fields of `CupchanN::BITS` bits each, followed by the number of unread storage cups.
+/// Position 0 holds the writer's cup, position 1 the reader's cup and positions `2..N` are storage cups ordered from oldest to newest flush.
+/// The unread cups are always the newest ones at the end of the storage positions.
+struct CupchanN<T, const N: usize> {
+	cups: [UnsafeCell<T>; N],
+	state: AtomicUsize,
+}
+impl<T, const N: usize> CupchanN<T, N> {
+	/// Bits used to store a single cup index in `state`
+	const BITS: usize = (usize::BITS - (N - 1).leading_zeros()) as usize;
+	const MASK: usize = (1 << Self::BITS) - 1;
+	/// Number of storage cups
+	const STORAGE: usize = N - 2;
+	/// Offset of the unread count in `state`
+	const LEN_SHIFT: usize = N * Self::BITS;
+	/// Fails compilation for cup counts that can't be packed into `state`
+	const VALID: () = assert!(
+		N >= 3 && Self::LEN_SHIFT + Self::BITS <= usize::BITS as usize,
+		"CupchanN needs at least 3 cups and no more than fit in the state word"
+	);
+
+	fn new(cups: [T; N]) -> Self {
+		#[allow(clippy::let_unit_value)]
+		let () = Self::VALID;
+		// Initial state: cup i at position i and nothing unread
+		let state = (0..N).fold(0, |state, pos| state | (pos << (pos * Self::BITS)));
+		Self {
+			cups: cups.map(UnsafeCell::new),
+			state: AtomicUsize::new(state),
+		}
+	}
+	#[inline]
+	fn cup(state: usize, pos: usize) -> usize {
+		(state >> (pos * Self::BITS)) & Self::MASK
+	}
+	#[inline]
+	fn set_cup(state: usize, pos: usize, cup: usize) -> usize {
+		(state & !(Self::MASK << (pos * Self::BITS))) | (cup << (pos * Self::BITS))
+	}
+	#[inline]
+	fn unread(state: usize) -> usize {
+		state >> Self::LEN_SHIFT
+	}
+	#[inline]
+	fn set_unread(state: usize, unread: usize) -> usize {
+		(state & ((1 << Self::LEN_SHIFT) - 1)) | (unread << Self::LEN_SHIFT)
+	}
+	/// Writer publishes its cup as the newest storage cup and takes the oldest one, dropping it if it was unread
+	fn write_transition(state: usize) -> usize {
+		let mut next = Self::set_cup(state, 0, Self::cup(state, 2));
+		for pos in 2..N - 1 {
+			next = Self::set_cup(next, pos, Self::cup(state, pos + 1));
+		}
+		next = Self::set_cup(next, N - 1, Self::cup(state, 0));
+		Self::set_unread(next, (Self::unread(state) + 1).min(Self::STORAGE))
+	}
+	/// Reader swaps its cup with the oldest unread storage cup, or keeps its cup if there is nothing new
+	fn read_transition(state: usize) -> usize {
+		let unread = Self::unread(state);
+		if unread == 0 {
+			return state;
+		}
+		let oldest = N - unread;
+		let next = Self::set_cup(state, 1, Self::cup(state, oldest));
+		let next = Self::set_cup(next, oldest, Self::cup(state, 1));
+		Self::set_unread(next, unread - 1)
+	}
+}
+impl<T: fmt::Debug, const N: usize> fmt::Debug for CupchanN<T, N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CupchanN")
+			.field("cups", &self.cups)
+			.field("state", &self.state.load(Ordering::SeqCst))
+			.finish()
+	}
+}
+
+/// Create a new Cup Channel with `N` cups.
+/// Up to `N - 2` flushes are queued for the reader, which reads them oldest first and keeps the latest once it has caught up.
+/// If the writer gets further ahead, the oldest unread values are dropped.
+/// ```rust
+/// use cupchan::cupchan_n;
+///
+/// let (mut writer, reader) = cupchan_n::<_, 4>(0);
+/// for i in 1..=3 {
+///     *writer = i;
+///     writer.flush();
+/// }
+/// assert_eq!(*reader, 2); // 1 was dropped, only 2 storage cups
+/// assert_eq!(*reader, 3);
+/// assert_eq!(*reader, 3);
+/// ```
+pub fn cupchan_n<T: Clone, const N: usize>(
+	initial: T,
+) -> (CupchanNWriter<T, N>, CupchanNReader<T, N>) {
+	let chan = Arc::new(CupchanN::new(core::array::from_fn(|_| initial.clone())));
+	(
+		CupchanNWriter {
+			chan: chan.clone(),
+			current_cup: 0,
+		},
+		CupchanNReader { chan },
+	)
+}
+
+/// Write to a [`cupchan_n`] channel, make sure to call flush() afterwards.
+#[derive(Debug)]
+pub struct CupchanNWriter<T, const N: usize> {
+	chan: Arc<CupchanN<T, N>>,
+	/// Index of the cup currently being written to
+	current_cup: usize,
+}
+impl<T, const N: usize> CupchanNWriter<T, N> {
+	/// Queue the writing cup for the reader
+	pub fn flush(&mut self) {
+		let res = self
+			.chan
+			.state
+			.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+				Some(CupchanN::<T, N>::write_transition(state))
+			})
+			.unwrap();
+		self.current_cup = CupchanN::<T, N>::cup(CupchanN::<T, N>::write_transition(res), 0);
+	}
+	#[cfg(loom)]
+	pub fn loom_ptr(&mut self) -> MutPtr<T> {
+		self.chan.cups[self.current_cup].get_mut()
+	}
+}
+#[cfg(not(loom))]
+impl<T, const N: usize> Deref for CupchanNWriter<T, N> {
+	type Target = T;
+	fn deref(&self) -> &Self::Target {
+		unsafe { &*self.chan.cups[self.current_cup].get() }
+	}
+}
+#[cfg(not(loom))]
+impl<T, const N: usize> DerefMut for CupchanNWriter<T, N> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		unsafe { &mut *self.chan.cups[self.current_cup].get() }
+	}
+}
+unsafe impl<T: Sync + Send, const N: usize> Send for CupchanNWriter<T, N> {}
+unsafe impl<T: Sync + Send, const N: usize> Sync for CupchanNWriter<T, N> {}
+
+/// Read from a [`cupchan_n`] channel by dereferencing this object, each deref moves on to the oldest unread value
+#[derive(Debug)]
+pub struct CupchanNReader<T, const N: usize> {
+	chan: Arc<CupchanN<T, N>>,
+}
+impl<T, const N: usize> CupchanNReader<T, N> {
+	#[inline]
+	fn read(&self) -> &UnsafeCell<T> {
+		let res = self
+			.chan
+			.state
+			.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+				Some(CupchanN::<T, N>::read_transition(state))
+			})
+			.unwrap();
+		&self.chan.cups[CupchanN::<T, N>::cup(CupchanN::<T, N>::read_transition(res), 1)]
+	}
+	/// Number of flushed values waiting to be read
+	pub fn unread(&self) -> usize {
+		CupchanN::<T, N>::unread(self.chan.state.load(Ordering::Acquire))
+	}
+	#[cfg(loom)]
+	pub fn loom_ptr(&self) -> ConstPtr<T> {
+		self.read().get()
+	}
+}
+#[cfg(not(loom))]
+impl<T, const N: usize> Deref for CupchanNReader<T, N> {
+	type Target = T;
+	fn deref(&self) -> &Self::Target {
+		unsafe { &*self.read().get() }
+	}
+}
+unsafe impl<T: Sync + Send, const N: usize> Send for CupchanNReader<T, N> {}
+unsafe impl<T: Sync + Send, const N: usize> Sync for CupchanNReader<T, N> {}
@@ -0,0 +1,56 @@
+//! Serde support, a channel is serialized as its latest value and deserialized into a new channel
+
+#[cfg(not(loom))]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(not(loom))]
+use crate::{CupchanReader, CupchanWriter};
+
+/// Serializes the latest flushed value, swapping it in like a deref would.
+/// A live channel can't be deserialized into, create a new one from the deserialized value instead:
+/// ```rust
+/// use cupchan::cupchan;
+///
+/// let (mut writer, reader) = cupchan(vec![1, 2]);
+/// *writer = vec![3];
+/// writer.flush();
+/// let json = serde_json::to_string(&reader).unwrap();
+/// assert_eq!(json, "[3]");
+///
+/// let (_writer, reader) = cupchan::<Vec<i32>>(serde_json::from_str(&json).unwrap());
+/// assert_eq!(*reader, [3]);
+/// ```
+#[cfg(not(loom))]
+impl<T: Serialize> Serialize for CupchanReader<T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		(**self).serialize(serializer)
+	}
+}
+
+#[cfg(not(loom))]
+impl<T> CupchanReader<T> {
+	/// Serialize the latest flushed value, same as the [`Serialize`] impl
+	#[inline]
+	pub fn serialize_value<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		T: Serialize,
+	{
+		Serialize::serialize(self, serializer)
+	}
+}
+
+#[cfg(not(loom))]
+impl<T> CupchanWriter<T> {
+	/// Deserialize a value into the writing cup, reusing its allocations where possible, and flush it.
+	/// Returns the same as [`flush`](CupchanWriter::flush), nothing is flushed if deserializing fails.
+	pub fn deserialize_into<'de, D: Deserializer<'de>>(
+		&mut self,
+		deserializer: D,
+	) -> Result<bool, D::Error>
+	where
+		T: Deserialize<'de>,
+	{
+		T::deserialize_in_place(deserializer, self)?;
+		Ok(self.flush())
+	}
+}
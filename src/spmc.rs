@@ -0,0 +1,178 @@
+//! Cup channels with cloneable and broadcast readers, for when several consumers want the latest value
+
+#[cfg(not(loom))]
+use crate::Ordering;
+use crate::{cupchan, Arc, AtomicBool, CupchanReader, CupchanWriter};
+
+/// A [`CupchanReader`] shared between all clones of a [`CupchanSharedReader`], reads are serialized by `locked`
+#[derive(Debug)]
+struct Shared<T> {
+	reader: CupchanReader<T>,
+	/// Spin lock held while a clone reads, the triple buffer only has a single reader cup
+	#[cfg_attr(loom, allow(dead_code))]
+	locked: AtomicBool,
+}
+
+/// Create a new Cup Channel whose reader can be cloned, see [`CupchanSharedReader`].
+/// ```rust
+/// use cupchan::cupchan_spmc;
+///
+/// let (mut writer, reader) = cupchan_spmc(0);
+/// let other = reader.clone();
+/// *writer = 1;
+/// writer.flush();
+/// assert_eq!(reader.read(), 1);
+/// assert_eq!(other.read(), 1);
+/// ```
+pub fn cupchan_spmc<T: Clone>(initial: T) -> (CupchanWriter<T>, CupchanSharedReader<T>) {
+	let (writer, reader) = cupchan(initial);
+	(writer, reader.into_shared())
+}
+
+impl<T> CupchanReader<T> {
+	/// Turn this reader into a [`CupchanSharedReader`] that can be cloned
+	pub fn into_shared(self) -> CupchanSharedReader<T> {
+		CupchanSharedReader {
+			shared: Arc::new(Shared {
+				reader: self,
+				locked: AtomicBool::new(false),
+			}),
+		}
+	}
+}
+
+/// Cloneable reader of a cup channel, every clone observes the latest flushed value.
+///
+/// The channel still has a single reader cup, so clones take turns reading it and get a clone of the value instead of a reference.
+/// This costs a [`Clone`] of `T` per read and a short spin if another clone is reading at the same time, the writer is never blocked.
+#[derive(Debug)]
+pub struct CupchanSharedReader<T> {
+	shared: Arc<Shared<T>>,
+}
+impl<T> Clone for CupchanSharedReader<T> {
+	fn clone(&self) -> Self {
+		Self {
+			shared: self.shared.clone(),
+		}
+	}
+}
+#[cfg(not(loom))]
+impl<T: Clone> CupchanSharedReader<T> {
+	/// Clone the latest flushed value
+	pub fn read(&self) -> T {
+		self.read_versioned().0
+	}
+	/// Clone the latest flushed value along with the generation it was flushed at (0 for the initial value)
+	pub fn read_versioned(&self) -> (T, usize) {
+		while self
+			.shared
+			.locked
+			.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+			.is_err()
+		{
+			core::hint::spin_loop();
+		}
+		// Release the lock even if clone() panics
+		let _unlock = Unlock(&self.shared.locked);
+		let (value, generation) = self.shared.reader.read_versioned();
+		(value.clone(), generation)
+	}
+}
+impl<T> CupchanSharedReader<T> {
+	/// Number of times the writer has flushed
+	pub fn generation(&self) -> usize {
+		self.shared.reader.generation()
+	}
+	/// Returns true if the writer hasn't been dropped or disconnected.
+	pub fn is_writer_connected(&self) -> bool {
+		self.shared.reader.is_writer_connected()
+	}
+}
+
+#[cfg(not(loom))]
+struct Unlock<'a>(&'a AtomicBool);
+#[cfg(not(loom))]
+impl Drop for Unlock<'_> {
+	fn drop(&mut self) {
+		self.0.store(false, Ordering::Release);
+	}
+}
+
+/// Create a new Cup Channel with any number of independent readers, see [`ReaderFactory::subscribe`].
+/// ```rust
+/// use cupchan::cupchan_broadcast;
+///
+/// let (mut writer, factory) = cupchan_broadcast(0);
+/// let mut a = factory.subscribe();
+/// let mut b = factory.subscribe();
+/// *writer = 1;
+/// writer.flush();
+/// assert_eq!(a.try_read(), Some(1));
+/// assert_eq!(a.try_read(), None);
+/// assert_eq!(b.try_read(), Some(1));
+/// ```
+pub fn cupchan_broadcast<T: Clone>(initial: T) -> (CupchanWriter<T>, ReaderFactory<T>) {
+	let (writer, reader) = cupchan_spmc(initial);
+	(writer, ReaderFactory { shared: reader })
+}
+
+/// Hands out [`BroadcastReader`]s of a [`cupchan_broadcast`] channel
+#[derive(Debug)]
+pub struct ReaderFactory<T> {
+	shared: CupchanSharedReader<T>,
+}
+impl<T> ReaderFactory<T> {
+	/// Create a new reader, the current value counts as unseen
+	pub fn subscribe(&self) -> BroadcastReader<T> {
+		BroadcastReader {
+			shared: self.shared.clone(),
+			seen: None,
+		}
+	}
+}
+impl<T> Clone for ReaderFactory<T> {
+	fn clone(&self) -> Self {
+		Self {
+			shared: self.shared.clone(),
+		}
+	}
+}
+
+/// Reader of a [`cupchan_broadcast`] channel that keeps track of which flush it has last seen.
+/// Built on [`CupchanSharedReader`], so reads clone the value and never block the writer.
+#[derive(Debug)]
+pub struct BroadcastReader<T> {
+	shared: CupchanSharedReader<T>,
+	/// Generation of the last value returned, None before the first read
+	seen: Option<usize>,
+}
+impl<T> BroadcastReader<T> {
+	/// Returns true if the writer has flushed since this reader last read
+	pub fn changed(&self) -> bool {
+		self.seen != Some(self.shared.generation())
+	}
+	/// Returns true if the writer hasn't been dropped or disconnected.
+	pub fn is_writer_connected(&self) -> bool {
+		self.shared.is_writer_connected()
+	}
+}
+#[cfg(not(loom))]
+impl<T: Clone> BroadcastReader<T> {
+	/// Clone the latest flushed value
+	pub fn read(&mut self) -> T {
+		let (value, generation) = self.shared.read_versioned();
+		self.seen = Some(generation);
+		value
+	}
+	/// Clone the latest flushed value if this reader hasn't seen it yet
+	pub fn try_read(&mut self) -> Option<T> {
+		if !self.changed() {
+			return None;
+		}
+		let (value, generation) = self.shared.read_versioned();
+		(self.seen != Some(generation)).then(|| {
+			self.seen = Some(generation);
+			value
+		})
+	}
+}
@@ -0,0 +1,152 @@
+//! Async [`Stream`] adapter and change notification for [`CupchanReader`] and flush notification for [`CupchanWriter`], enabled with the `async` feature
+
+use core::{
+	future::Future,
+	pin::Pin,
+	task::{Context, Poll, Waker},
+};
+
+#[cfg(not(loom))]
+use futures_core::Stream;
+
+use crate::{Cupchan, CupchanReader, CupchanWriter, Disconnected, Ordering, ALIVE, UPDATE_FLAG};
+
+impl<T> Cupchan<T> {
+	/// Wake the pending stream or [`Changed`] future if there is one, called by every flush.
+	/// Only a load if nothing is registered, the flush's RMW on `state` orders it against [`poll_changed`](CupchanReader::poll_changed) registering.
+	#[inline]
+	pub(crate) fn wake_stream(&self) {
+		self.stream_waker.wake_registered();
+	}
+}
+
+impl<T> CupchanReader<T> {
+	/// Turn this reader into a [`Stream`] that yields the latest value each time the writer flushes
+	pub fn into_stream(self) -> CupchanStream<T> {
+		CupchanStream { reader: self }
+	}
+	/// Store `waker` to be woken by the next flush or when the writer is dropped, for building custom `poll` based wrappers.
+	/// Only one waker is stored and it is shared with [`changed_async`](Self::changed_async) and [`CupchanStream`], the last registration wins.
+	/// Check [`changed`](Self::changed) after registering in case a flush happened just before.
+	pub fn register_waker(&self, waker: &Waker) {
+		self.chan.stream_waker.register(waker);
+		// Order the caller's check against a concurrent flush, like poll_changed does
+		self.chan.state.fetch_or(0, Ordering::AcqRel);
+	}
+	/// Wait until the writer flushes a value that hasn't been read yet, like `tokio::sync::watch::Receiver::changed`.
+	/// Resolves immediately if there already is one and to [`Disconnected`] if the writer is dropped first.
+	/// The value isn't read, deref the reader afterwards. Dropping the future before it resolves is fine.
+	pub fn changed_async(&mut self) -> Changed<'_, T> {
+		Changed { reader: self }
+	}
+	/// Poll for a flush that hasn't been read yet, the building block of [`changed_async`](Self::changed_async) for custom executors.
+	/// Returns [`Disconnected`] once the writer is dropped with nothing left to read.
+	/// Registers the waker in the same single slot as [`register_waker`](Self::register_waker) when pending, so it can be polled again after being cancelled.
+	pub fn poll_changed(&self, cx: &mut Context<'_>) -> Poll<Result<(), Disconnected>> {
+		if let Some(res) = self.ready() {
+			return Poll::Ready(res);
+		}
+		self.chan.stream_waker.register(cx.waker());
+		// Check again now that the waker is registered in case the writer flushed or dropped in between.
+		// The RMW orders this against a concurrent flush, which either sees the waker or is seen here
+		self.chan.state.fetch_or(0, Ordering::AcqRel);
+		match self.ready() {
+			Some(res) => Poll::Ready(res),
+			None => Poll::Pending,
+		}
+	}
+	#[inline]
+	fn ready(&self) -> Option<Result<(), Disconnected>> {
+		if self.changed() {
+			Some(Ok(()))
+		} else if self.chan.writer_dropped() {
+			Some(Err(Disconnected))
+		} else {
+			None
+		}
+	}
+}
+
+/// Future returned by [`CupchanReader::changed_async`]
+#[derive(Debug)]
+pub struct Changed<'a, T> {
+	reader: &'a mut CupchanReader<T>,
+}
+impl<T> Future for Changed<'_, T> {
+	type Output = Result<(), Disconnected>;
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		self.reader.poll_changed(cx)
+	}
+}
+impl<T> Drop for Changed<'_, T> {
+	fn drop(&mut self) {
+		// Deregister so a later flush doesn't wake a task that is no longer waiting
+		drop(self.reader.chan.stream_waker.take());
+	}
+}
+
+/// Stream of the latest flushed values, created by [`CupchanReader::into_stream`].
+/// Multiple flushes between polls are coalesced into a single item, the stream ends once the writer is dropped.
+#[derive(Debug)]
+pub struct CupchanStream<T> {
+	reader: CupchanReader<T>,
+}
+impl<T> CupchanStream<T> {
+	/// Get back the underlying reader
+	pub fn into_inner(self) -> CupchanReader<T> {
+		self.reader
+	}
+}
+#[cfg(not(loom))]
+impl<T: Clone> Stream for CupchanStream<T> {
+	type Item = T;
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+		let reader = &self.get_mut().reader;
+		match core::task::ready!(reader.poll_changed(cx)) {
+			Ok(()) => Poll::Ready(Some((**reader).clone())),
+			Err(Disconnected) => Poll::Ready(None),
+		}
+	}
+}
+
+impl<T> CupchanWriter<T> {
+	/// Flush, then wait until the reader has read the flushed value.
+	/// The flush happens immediately, the returned future resolves once the value is consumed or the reader is dropped.
+	pub fn flush_async(&mut self) -> FlushFuture<'_, T> {
+		self.flush();
+		FlushFuture { writer: self }
+	}
+}
+
+/// Future returned by [`CupchanWriter::flush_async`]
+#[derive(Debug)]
+pub struct FlushFuture<'a, T> {
+	writer: &'a mut CupchanWriter<T>,
+}
+impl<T> FlushFuture<'_, T> {
+	#[inline]
+	fn consumed(&self) -> bool {
+		let chan = &self.writer.chan;
+		chan.state.load(Ordering::Acquire) & UPDATE_FLAG == 0
+			|| chan.reader_state.load(Ordering::Acquire) != ALIVE
+	}
+}
+impl<T> Future for FlushFuture<'_, T> {
+	type Output = ();
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+		if self.consumed() {
+			return Poll::Ready(());
+		}
+		let chan = &self.writer.chan;
+		chan.writer_waker.register(cx.waker());
+		// Check again now that the waker is registered in case the reader read or dropped in between.
+		// The RMWs order this against the reader's swap or disconnect, which either sees the waker or is seen here
+		chan.state.fetch_or(0, Ordering::AcqRel);
+		chan.reader_state.fetch_or(0, Ordering::AcqRel);
+		if self.consumed() {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}
+}
@@ -0,0 +1,108 @@
+//! Lock-free cell holding a single [`Waker`], modeled after `futures::task::AtomicWaker`
+
+use core::{
+	cell::UnsafeCell,
+	fmt,
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	task::Waker,
+};
+
+/// No one is touching the waker cell
+const WAITING: usize = 0;
+/// A new waker is being stored
+const REGISTERING: usize = 0b01;
+/// The stored waker is being taken out to be woken
+const WAKING: usize = 0b10;
+
+/// Waker slot shared between the reader (registering) and the writer (waking)
+pub(crate) struct AtomicWaker {
+	state: AtomicUsize,
+	waker: UnsafeCell<Option<Waker>>,
+	/// True while the cell may hold a waker, lets [`wake_registered`](Self::wake_registered) skip the RMWs with a plain load
+	registered: AtomicBool,
+}
+impl AtomicWaker {
+	pub(crate) const fn new() -> Self {
+		Self {
+			state: AtomicUsize::new(WAITING),
+			waker: UnsafeCell::new(None),
+			registered: AtomicBool::new(false),
+		}
+	}
+	/// Store `waker` to be woken by the next call to [`wake`](Self::wake).
+	/// Must not be called concurrently with itself.
+	pub(crate) fn register(&self, waker: &Waker) {
+		match self
+			.state
+			.compare_exchange(WAITING, REGISTERING, Ordering::Acquire, Ordering::Acquire)
+			.unwrap_or_else(|state| state)
+		{
+			WAITING => {
+				// We have exclusive access to the cell until state is set back to WAITING
+				let old = unsafe {
+					match &mut *self.waker.get() {
+						Some(old) if old.will_wake(waker) => None,
+						slot => slot.replace(waker.clone()),
+					}
+				};
+				// Published by setting state back to WAITING below
+				self.registered.store(true, Ordering::Relaxed);
+				if self
+					.state
+					.compare_exchange(REGISTERING, WAITING, Ordering::AcqRel, Ordering::Acquire)
+					.is_err()
+				{
+					// A wake happened while registering, the waker couldn't take the waker so do it ourselves
+					let waker = unsafe { (*self.waker.get()).take() };
+					self.registered.store(false, Ordering::Relaxed);
+					self.state.swap(WAITING, Ordering::AcqRel);
+					if let Some(waker) = waker {
+						waker.wake();
+					}
+				}
+				drop(old);
+			}
+			WAKING => {
+				// Currently being woken, make sure the new waker is woken too
+				waker.wake_by_ref();
+			}
+			state => {
+				debug_assert!(state == REGISTERING || state == REGISTERING | WAKING);
+			}
+		}
+	}
+	/// Wake the registered waker if there is one
+	pub(crate) fn wake(&self) {
+		if let Some(waker) = self.take() {
+			waker.wake();
+		}
+	}
+	/// Same as [`wake`](Self::wake) but only costs a load when no waker is registered, for hot paths.
+	/// The caller must do an RMW on an atomic the registering side also does an RMW on after registering, otherwise the load can miss a registration.
+	#[inline]
+	pub(crate) fn wake_registered(&self) {
+		if self.registered.load(Ordering::Acquire) {
+			self.wake();
+		}
+	}
+	/// Take the registered waker out without waking it
+	pub(crate) fn take(&self) -> Option<Waker> {
+		if let WAITING = self.state.fetch_or(WAKING, Ordering::AcqRel) {
+			let waker = unsafe { (*self.waker.get()).take() };
+			self.registered.store(false, Ordering::Relaxed);
+			self.state.fetch_and(!WAKING, Ordering::Release);
+			waker
+		} else {
+			None
+		}
+	}
+}
+impl fmt::Debug for AtomicWaker {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AtomicWaker")
+			.field("state", &self.state.load(Ordering::SeqCst))
+			.finish()
+	}
+}
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}
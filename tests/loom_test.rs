@@ -12,7 +12,7 @@ fn loom_test() {
 		const MAX: usize = 4;
 		let join = thread::spawn(move || {
 			for i in 0..MAX {
-				let ptr = writer.loom_ptr();
+				let mut ptr = writer.loom_ptr();
 				unsafe {
 					*ptr.deref() = i;
 				}
@@ -39,3 +39,137 @@ fn loom_test() {
 		join.join().unwrap();
 	});
 }
+
+/// Every flush writes a distinct value spread over two fields, the reader must never see them torn or going backwards
+#[test]
+fn loom_distinct_values() {
+	loom::model(|| {
+		let (mut writer, reader) = cupchan((0, 0));
+
+		const MAX: usize = 3;
+		let join = thread::spawn(move || {
+			for i in 1..=MAX {
+				let mut ptr = writer.loom_ptr();
+				unsafe {
+					let cup = &mut *ptr.deref();
+					cup.0 = i;
+					cup.1 = i * 10;
+				}
+				drop(ptr);
+				writer.flush();
+			}
+		});
+
+		let mut current = 0;
+		while current < MAX {
+			let ptr = reader.loom_ptr();
+			let (value, tag) = unsafe { *ptr.deref() };
+			drop(ptr);
+			assert_eq!(tag, value * 10, "torn read");
+			assert!(current <= value, "read {value} after {current}");
+			// The generation is stored before the flush that published this value
+			assert!(reader.generation() >= value);
+			current = value;
+			thread::yield_now();
+		}
+
+		join.join().unwrap();
+	});
+}
+
+/// Counts how many times a cup is dropped
+#[derive(Clone)]
+struct DropCount(loom::sync::Arc<loom::sync::atomic::AtomicUsize>);
+impl Drop for DropCount {
+	fn drop(&mut self) {
+		self.0.fetch_add(1, loom::sync::atomic::Ordering::SeqCst);
+	}
+}
+
+/// Reconnecting races with the other endpoint being dropped, every cup must still be dropped exactly once
+#[test]
+fn loom_reconnect() {
+	use loom::sync::{atomic::AtomicUsize, Arc};
+	loom::model(|| {
+		let drops = Arc::new(AtomicUsize::new(0));
+		let (writer, reader) = cupchan(DropCount(drops.clone()));
+
+		let join = thread::spawn(move || drop(reader));
+		// Either the reader was still connected, or this is the only reconnect
+		let reader = writer.new_reader();
+		join.join().unwrap();
+		let reader = reader.or_else(|_| writer.new_reader()).unwrap();
+		assert!(writer.new_reader().is_err());
+
+		// Same the other way around, with both endpoints dropped at the end
+		let join = thread::spawn(move || drop(writer));
+		let writer = reader.new_writer();
+		join.join().unwrap();
+		let writer = writer.or_else(|_| reader.new_writer()).unwrap();
+
+		let join = thread::spawn(move || drop(writer));
+		drop(reader);
+		join.join().unwrap();
+		assert_eq!(drops.load(loom::sync::atomic::Ordering::SeqCst), 3);
+	});
+}
+
+/// Writes through the writer's cup pointer and flushes in between, loom checks each cup access against the reader's reads
+#[test]
+fn loom_write_between_flushes() {
+	loom::model(|| {
+		let (mut writer, reader) = cupchan(0);
+
+		let join = thread::spawn(move || {
+			for i in 0..2 {
+				// Both writes have to go through the pointer before it's released for the flush
+				let mut ptr = writer.loom_ptr();
+				unsafe {
+					*ptr.deref() = 2 * i + 1;
+					*ptr.deref() = 2 * i + 2;
+				}
+				drop(ptr);
+				writer.flush();
+			}
+		});
+
+		let mut last = 0;
+		for _ in 0..2 {
+			let ptr = reader.loom_ptr();
+			let read = unsafe { *ptr.deref() };
+			drop(ptr);
+			// Only flushed values, never the odd one written in between
+			assert!([0, 2, 4].contains(&read), "read unflushed value {read}");
+			assert!(read >= last);
+			last = read;
+			thread::yield_now();
+		}
+
+		join.join().unwrap();
+	});
+}
+
+/// A reader that disconnects and is then dropped can be replaced exactly once, and only after the drop
+#[test]
+fn loom_disconnect() {
+	loom::model(|| {
+		let (writer, reader) = cupchan(0);
+
+		let join = thread::spawn(move || {
+			reader.disconnect();
+			drop(reader);
+		});
+		let early = writer.new_reader();
+		join.join().unwrap();
+		let reader = match early {
+			// The disconnected reader was already dropped, so the channel must look fully connected again
+			Ok(reader) => {
+				assert!(writer.new_reader().is_err());
+				reader
+			}
+			Err(_) => writer.new_reader().unwrap(),
+		};
+		assert!(writer.is_reader_connected());
+		assert!(reader.is_writer_connected());
+	});
+}